@@ -0,0 +1,222 @@
+//! # Range Boundaries
+//!
+//! Boundaries of a range can be full timestamps or expressions that are resolved against the current time or the other boundary:
+//!
+//! * `now`, `today`, `yesterday` and `tomorrow`, days start at midnight in the timezone provided.
+//! * Partial dates such as `2021`, `2021-03`, `2021-03-01` or `2021-03-01 10`, these cover the whole year, month, day or hour.
+//! * Relative times such as `-2h`, `-P1D` or `+30m` (any increment without months or years). A start is relative to now, an end starting with `+` is relative to the start.
+//!
+//! A start covering a period (a partial date or a day) with no end is the range of that period, otherwise a range with no end finishes now.
+//!
+
+use crate::{aggregators::Increment, error, Data, Result, Timezone};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+lazy_static! {
+    static ref PARTIAL_DATE_REGEX: regex::Regex = regex::Regex::new(
+        r"^(?P<year>\d{4})(?:-(?P<month>\d{2})(?:-(?P<day>\d{2})(?:[T ](?P<hour>\d{2})(?::(?P<minute>\d{2}))?)?)?)?$"
+    )
+    .unwrap();
+}
+
+/// A range boundary expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expression {
+    /// A single point in time.
+    Instant(DateTime<FixedOffset>),
+    /// A period of time, the end is the last nanosecond of the period.
+    Period(DateTime<FixedOffset>, DateTime<FixedOffset>),
+    /// A time relative to now or to the other boundary.
+    Relative(Duration),
+}
+
+impl Expression {
+    /// Parse an expression, `now` is the time relative expressions and days are resolved against.
+    pub fn new(s: &str, tz: Option<&String>, now: DateTime<Utc>) -> Result<Self> {
+        let timezone = timezone(tz)?;
+        let local_now = timezone.from_utc(&now);
+        let day = |days: i64| {
+            let date = local_now.date_naive() + Duration::days(days);
+            period(
+                &timezone,
+                local_now,
+                midnight(date),
+                midnight(date + Duration::days(1)),
+            )
+        };
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "now" => return Ok(Expression::Instant(local_now)),
+            "today" => return Ok(day(0)),
+            "yesterday" => return Ok(day(-1)),
+            "tomorrow" => return Ok(day(1)),
+            _ => (),
+        }
+        if let Some(captures) = PARTIAL_DATE_REGEX.captures(s) {
+            let number = |name: &str| captures.name(name).map(|m| m.as_str().parse::<u32>());
+            let year: i32 = captures["year"].parse()?;
+            let invalid = || error::Error {
+                reason: format!("{} is not a valid date", s),
+                kind: error::ErrorKind::DateTime,
+            };
+            let (start, end) = match (number("month"), number("day"), number("hour")) {
+                (None, _, _) => (
+                    NaiveDate::from_ymd_opt(year, 1, 1),
+                    NaiveDate::from_ymd_opt(year + 1, 1, 1),
+                ),
+                (Some(month), None, _) => {
+                    let month = month?;
+                    let (next_year, next_month) = match month {
+                        12 => (year + 1, 1),
+                        m => (year, m + 1),
+                    };
+                    (
+                        NaiveDate::from_ymd_opt(year, month, 1),
+                        NaiveDate::from_ymd_opt(next_year, next_month, 1),
+                    )
+                }
+                (Some(month), Some(d), None) => {
+                    let start = NaiveDate::from_ymd_opt(year, month?, d?);
+                    (start, start.map(|d| d + Duration::days(1)))
+                }
+                (Some(month), Some(d), Some(hour)) => {
+                    let date = NaiveDate::from_ymd_opt(year, month?, d?).ok_or_else(invalid)?;
+                    let start = date.and_hms_opt(hour?, number("minute").unwrap_or(Ok(0))?, 0);
+                    let length = match captures.name("minute") {
+                        Some(_) => Duration::minutes(1),
+                        None => Duration::hours(1),
+                    };
+                    let start = start.ok_or_else(invalid)?;
+                    return Ok(period(&timezone, local_now, start, start + length));
+                }
+            };
+            return match (start, end) {
+                (Some(start), Some(end)) => {
+                    Ok(period(&timezone, local_now, midnight(start), midnight(end)))
+                }
+                _ => Err(invalid()),
+            };
+        }
+        // Offsets keep a leading `-` so they are negative
+        let relative = match s.strip_prefix('+') {
+            Some(r) => Some(r),
+            None => Some(s).filter(|s| s.starts_with('-')),
+        };
+        if let Some(Ok(offset)) = relative.map(Increment::parse_offset) {
+            return Ok(Expression::Relative(offset));
+        }
+        Ok(Expression::Instant(
+            Data::new(s, None, None, tz, Vec::new())?.timestamp,
+        ))
+    }
+}
+
+fn timezone(tz: Option<&String>) -> Result<Timezone> {
+    match tz {
+        Some(t) => Timezone::new(t),
+        None => Ok(Timezone::default()),
+    }
+}
+
+fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+/// A period between two local times, the end is made inclusive.
+fn period(
+    timezone: &Timezone,
+    now: DateTime<FixedOffset>,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Expression {
+    Expression::Period(
+        timezone.boundary(&start, *now.offset()),
+        timezone.boundary(&end, *now.offset()) - Duration::nanoseconds(1),
+    )
+}
+
+/// Resolve the start and (inclusive) end of a range from boundary expressions.
+pub fn resolve(
+    start: &str,
+    end: Option<&str>,
+    tz: Option<&String>,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let local_now = timezone(tz)?.from_utc(&now);
+    let start_expression = Expression::new(start, tz, now)?;
+    let start_time = match start_expression {
+        Expression::Instant(t) | Expression::Period(t, _) => t,
+        Expression::Relative(offset) => local_now + offset,
+    };
+    let end_time = match (end, end.map(|e| Expression::new(e, tz, now)).transpose()?) {
+        (_, None) => match start_expression {
+            Expression::Period(_, e) => e,
+            _ => local_now,
+        },
+        (Some(e), Some(Expression::Relative(offset))) if e.trim().starts_with('+') => {
+            start_time + offset
+        }
+        (_, Some(Expression::Relative(offset))) => local_now + offset,
+        (_, Some(Expression::Instant(t))) | (_, Some(Expression::Period(_, t))) => t,
+    };
+    if end_time < start_time {
+        return Err(error::Error {
+            reason: format!(
+                "The end of the range ({}) is before the start ({})",
+                end_time, start_time
+            ),
+            kind: error::ErrorKind::Aggregator,
+        });
+    }
+    debug!("Range resolved to {} - {}", start_time, end_time);
+    Ok((start_time, end_time))
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn resolve_expressions() {
+        let now = DateTime::parse_from_rfc3339("2021-03-10T12:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let range = |start: &str, end: Option<&str>| {
+            let (s, e) = resolve(start, end, None, now).unwrap();
+            (s.to_rfc3339(), e.to_rfc3339())
+        };
+        assert_eq!(
+            range("-2h", None),
+            (
+                "2021-03-10T10:00:00+00:00".to_string(),
+                "2021-03-10T12:00:00+00:00".to_string()
+            )
+        );
+        assert_eq!(
+            range("yesterday", None),
+            (
+                "2021-03-09T00:00:00+00:00".to_string(),
+                "2021-03-09T23:59:59.999999999+00:00".to_string()
+            )
+        );
+        assert_eq!(
+            range("2021-03", None),
+            (
+                "2021-03-01T00:00:00+00:00".to_string(),
+                "2021-03-31T23:59:59.999999999+00:00".to_string()
+            )
+        );
+        assert_eq!(
+            range("2021-03-01", Some("+6h")),
+            (
+                "2021-03-01T00:00:00+00:00".to_string(),
+                "2021-03-01T06:00:00+00:00".to_string()
+            )
+        );
+        assert_eq!(
+            range("2021-03-01 10:00:00", Some("-1h")).1,
+            "2021-03-10T11:00:00+00:00"
+        );
+        assert!(resolve("today", Some("2021-01"), None, now).is_err());
+    }
+}
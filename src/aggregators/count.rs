@@ -1,26 +1,28 @@
 use crate::{
-    aggregators::{Aggregator, Increment},
+    aggregators::{Aggregator, Alignment, Increment},
     Data, Result,
 };
 use chrono::{DateTime, FixedOffset};
 use std::{collections::HashMap, convert::TryFrom};
 
+#[derive(Default)]
 pub struct CountAggregator {
     pub n: u64,
-}
-impl Default for CountAggregator {
-    fn default() -> Self {
-        Self { n: 0 }
-    }
+    /// How many of the timestamps counted had missing parts inferred.
+    pub inferred: u64,
 }
 impl Aggregator for CountAggregator {
-    fn update(&mut self, _data: &Data) -> Result<()> {
+    fn update(&mut self, data: &Data) -> Result<()> {
         self.n += 1;
+        self.inferred += data.inferred as u64;
         debug!("Updated Maximum Aggregator State: {:?}", self.n);
         Ok(())
     }
     fn return_value(&self) -> Result<String> {
-        Ok(format!("Count: {}", self.n))
+        match self.inferred {
+            0 => Ok(format!("Count: {}", self.n)),
+            i => Ok(format!("Count: {} ({} inferred)", self.n, i)),
+        }
     }
 }
 
@@ -35,6 +37,10 @@ impl CountAggregator {
 
 pub struct CountsAggregator {
     pub counts: HashMap<DateTime<FixedOffset>, u64>,
+    /// Counts for each tag in an increment, such as the MACB roles of bodyfile entries.
+    pub tagged: HashMap<DateTime<FixedOffset>, HashMap<String, u64>>,
+    /// Counts of the timestamps in an increment that had missing parts inferred.
+    pub inferred: HashMap<DateTime<FixedOffset>, u64>,
     pub increment: Increment,
 }
 
@@ -46,14 +52,35 @@ impl Aggregator for CountsAggregator {
             Some(c) => self.counts.insert(rounded, c + 1),
             None => self.counts.insert(rounded, 1),
         };
+        if let Some(tag) = &data.tag {
+            *self
+                .tagged
+                .entry(rounded)
+                .or_default()
+                .entry(tag.clone())
+                .or_insert(0) += 1;
+        }
+        if data.inferred {
+            *self.inferred.entry(rounded).or_insert(0) += 1;
+        }
         Ok(())
     }
     fn return_value(&self) -> Result<String> {
         let mut pretty: String = String::new();
         for (k, v) in self.counts.iter() {
-            pretty.push_str(&format!("\n{}: {}", k.with_timezone(&chrono::Utc), v));
+            // Buckets are shown in the timezone their boundaries were found in
+            pretty.push_str(&format!("\n{}: {}", k, v));
+            if let Some(tags) = self.tagged.get(k) {
+                let mut tags: Vec<(&String, &u64)> = tags.iter().collect();
+                tags.sort();
+                let tags: Vec<String> = tags.iter().map(|(t, c)| format!("{}: {}", t, c)).collect();
+                pretty.push_str(&format!(" ({})", tags.join(", ")));
+            }
+            if let Some(i) = self.inferred.get(k) {
+                pretty.push_str(&format!(" [{} inferred]", i));
+            }
         }
-        Ok(format!("Maximums for increment: {}", pretty))
+        Ok(format!("Counts for increment: {}", pretty))
     }
 }
 
@@ -62,10 +89,41 @@ impl CountsAggregator {
         Ok(Self {
             increment: Increment::try_from(increment)?,
             counts: HashMap::new(),
+            tagged: HashMap::new(),
+            inferred: HashMap::new(),
         })
     }
+    /// Set the timezone, origin and offset the increment's bucket boundaries are found with.
+    pub fn align(mut self, alignment: Alignment) -> Self {
+        self.increment = self.increment.align(alignment);
+        self
+    }
     pub fn output(&self) -> Result<HashMap<DateTime<FixedOffset>, u64>> {
         debug!("Maximum Aggregator returning output: {:#?}", self.counts);
         Ok(self.counts.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn count_inferred_timestamps() {
+        let data = |s: &str, inferred: bool| Data {
+            inferred,
+            ..Data::new(s, None, None, None, Vec::new()).unwrap()
+        };
+        let mut aggregator = CountsAggregator::new("1d".to_string()).unwrap();
+        aggregator
+            .update(&data("2021-03-01 10:00:00", false))
+            .unwrap();
+        aggregator
+            .update(&data("2021-03-01 11:00:00", true))
+            .unwrap();
+        assert_eq!(
+            aggregator.return_value().unwrap(),
+            "Counts for increment: \n2021-03-01 00:00:00 +00:00: 2 [1 inferred]"
+        );
+    }
+}
@@ -4,22 +4,17 @@
 //!
 
 use crate::{
-    aggregators::{Aggregator, Increment},
+    aggregators::{Aggregator, Alignment, Increment},
     error, Data, Result,
 };
 use chrono::{DateTime, FixedOffset};
 use std::{collections::HashMap, convert::TryFrom};
 
+#[derive(Default)]
 pub struct MaximumAggregator {
     pub largest: Option<Data>,
 }
 
-impl Default for MaximumAggregator {
-    fn default() -> Self {
-        Self { largest: None }
-    }
-}
-
 impl Aggregator for MaximumAggregator {
     fn update(&mut self, data: &Data) -> Result<()> {
         if let Some(largest) = &self.largest {
@@ -89,6 +84,11 @@ impl MaximumsAggregator {
             largests: HashMap::new(),
         })
     }
+    /// Set the timezone, origin and offset the increment's bucket boundaries are found with.
+    pub fn align(mut self, alignment: Alignment) -> Self {
+        self.increment = self.increment.align(alignment);
+        self
+    }
     pub fn output(&self) -> Result<HashMap<DateTime<FixedOffset>, Data>> {
         debug!("Maximum Aggregator returning output: {:#?}", self.largests);
         Ok(self.largests.clone())
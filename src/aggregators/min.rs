@@ -4,22 +4,17 @@
 //!
 
 use crate::{
-    aggregators::{Aggregator, Increment},
+    aggregators::{Aggregator, Alignment, Increment},
     error, Data, Result,
 };
 use chrono::{DateTime, FixedOffset};
 use std::{collections::HashMap, convert::TryFrom};
 
+#[derive(Default)]
 pub struct MinimumAggregator {
     pub smallest: Option<Data>,
 }
 
-impl Default for MinimumAggregator {
-    fn default() -> Self {
-        Self { smallest: None }
-    }
-}
-
 impl Aggregator for MinimumAggregator {
     fn update(&mut self, data: &Data) -> Result<()> {
         if let Some(smallest) = &self.smallest {
@@ -89,6 +84,11 @@ impl MinimumsAggregator {
             smallests: HashMap::new(),
         })
     }
+    /// Set the timezone, origin and offset the increment's bucket boundaries are found with.
+    pub fn align(mut self, alignment: Alignment) -> Self {
+        self.increment = self.increment.align(alignment);
+        self
+    }
     pub fn output(&self) -> Result<HashMap<DateTime<FixedOffset>, Data>> {
         debug!(
             "Minimums Aggregator returning output: {:#?}",
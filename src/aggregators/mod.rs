@@ -3,34 +3,61 @@
 //! The aggregator functions are a key part of `dta`. Some aggregation functions include:
 //!
 //! * WIP:
-//! ** Range
-//! ** Split
-//! ** Maximum
-//! ** Minimum
+//!   * Range
+//!   * Split
+//!   * Maximum
+//!   * Minimum
 //!
 //! Note: Not selecting an aggregator when running the `dta` binary will pass the data along
 //!
+pub mod boundary;
 pub mod count;
 pub mod max;
 pub mod min;
 pub mod range;
+pub mod recurring;
 pub mod split;
 
-use crate::{error, Data, Result};
-use chrono::{DateTime, Duration, FixedOffset};
+use crate::{error, Data, Result, Timezone};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Utc};
 use regex::Regex;
 use std::convert::TryFrom;
 
 pub trait Aggregator {
     fn update(&mut self, data: &Data) -> Result<()>;
     fn return_value(&self) -> Result<String>;
+    /// Return the state of the aggregation so far whilst data is still being read (for example when following a file).
+    /// By default this is the same as [`return_value`](Aggregator::return_value).
+    fn snapshot(&mut self) -> Result<String> {
+        self.return_value()
+    }
+    /// Provide the header of the data being aggregated, aggregators that output the raw data can write it first.
+    /// It is provided again whenever it changes, entries after that follow the new header.
+    fn set_header(&mut self, _header: Vec<u8>) {}
 }
 
-/// Increment can be used with the rounding trait in chrono todo aggregations by a variable increment.
+/// Increment is used to group timestamps into buckets by rounding them down to the start of their bucket.
+///
+/// Increments are calendar aware, the years and months of an increment are calendar months so buckets of a month, quarter or year start on the first of a month.
+/// Buckets of whole days start at midnight and buckets of whole weeks start on a Monday, as ISO weeks do.
+/// Boundaries are found in the [`Alignment`] of the increment, which is UTC with no origin or offset by default.
 #[derive(Debug)]
 pub struct Increment {
+    months: i64,
     duration: Duration,
     r#type: IncrementType,
+    alignment: Alignment,
+}
+
+/// The frame bucket boundaries are found in, so data with different offsets is bucketed consistently.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Alignment {
+    /// The timezone boundaries are found in.
+    pub timezone: Timezone,
+    /// A boundary that the other boundaries are counted from, calendar month increments ignore it.
+    pub origin: Option<DateTime<FixedOffset>>,
+    /// Moves every boundary later, such as shifts that start at 06:00.
+    pub offset: Duration,
 }
 #[derive(Debug)]
 pub enum IncrementType {
@@ -51,26 +78,119 @@ impl Increment {
     ) -> Self {
         Self {
             r#type,
-            duration: Duration::weeks(years * 52)
-                + Duration::days(months * 30)
-                + Duration::days(days)
+            months: years * 12 + months,
+            duration: Duration::days(days)
                 + Duration::hours(hours)
                 + Duration::minutes(minutes)
                 + Duration::seconds(seconds),
+            alignment: Alignment::default(),
+        }
+    }
+    /// Create an increment of calendar months and an exact duration.
+    fn from_duration(months: i64, duration: Duration) -> Self {
+        Self {
+            r#type: IncrementType::DateTime,
+            months,
+            duration,
+            alignment: Alignment::default(),
+        }
+    }
+    /// Check the increment is not empty.
+    fn checked(self, s: &str) -> Result<Self> {
+        match self.months > 0 || self.duration > Duration::zero() {
+            true => Ok(self),
+            false => Err(error::Error {
+                reason: format!("Increment {} is empty, it has to be longer than zero", s),
+                kind: error::ErrorKind::Increment,
+            }),
         }
     }
+    /// Set the timezone bucket boundaries are found in.
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.alignment.timezone = timezone;
+        self
+    }
+    /// Set the timezone, origin and offset bucket boundaries are found with.
+    pub fn align(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+    /// Parse an offset for an [`Alignment`], any increment without months or years can be used and a leading `-` moves boundaries earlier.
+    pub fn parse_offset(s: &str) -> Result<Duration> {
+        let (negative, increment) = match s.strip_prefix('-') {
+            Some(i) => (true, i),
+            None => (false, s),
+        };
+        let increment = Self::try_from(increment.to_string())?;
+        if increment.months != 0 {
+            return Err(error::Error {
+                reason: format!("Offset {} cannot include months or years", s),
+                kind: error::ErrorKind::Increment,
+            });
+        }
+        Ok(match negative {
+            true => -increment.duration,
+            false => increment.duration,
+        })
+    }
 
     pub fn rounded(&self, dt: DateTime<FixedOffset>) -> Result<DateTime<FixedOffset>> {
-        use chrono::DurationRound;
-        match self.r#type {
-            IncrementType::Date => Ok(dt.duration_trunc(self.duration)?),
-            IncrementType::Time => Ok(dt.duration_trunc(self.duration)?),
-            IncrementType::DateTime => Ok(dt.duration_trunc(self.duration)?),
+        if self.months <= 0 && self.duration <= Duration::zero() {
+            return Err(error::Error {
+                reason: format!("Increment of {:?} is empty", self.r#type),
+                kind: error::ErrorKind::Increment,
+            });
         }
-        // Ok(dt)
+        let timezone = self.alignment.timezone;
+        let local = timezone.from_utc(&dt.with_timezone(&Utc));
+        let naive = local.naive_local() - self.alignment.offset;
+        // Round down to the start of a calendar month
+        let mut start = match self.months > 0 {
+            true => {
+                let month = i64::from(naive.year()) * 12 + i64::from(naive.month0());
+                let month = month - month.rem_euclid(self.months);
+                NaiveDate::from_ymd_opt(
+                    month.div_euclid(12) as i32,
+                    month.rem_euclid(12) as u32 + 1,
+                    1,
+                )
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .ok_or(error::Error {
+                    reason: format!("{} cannot be rounded to a month", dt),
+                    kind: error::ErrorKind::Increment,
+                })?
+            }
+            false => match self.alignment.origin {
+                Some(origin) => {
+                    timezone.from_utc(&origin.with_timezone(&Utc)).naive_local()
+                        - self.alignment.offset
+                }
+                None => *WEEK_ORIGIN,
+            },
+        };
+        // Then round down the rest of the increment from there
+        if self.duration > Duration::zero() {
+            let nanos = |d: Duration| {
+                let seconds = d.num_seconds();
+                i128::from(seconds) * 1_000_000_000
+                    + i128::from((d - Duration::seconds(seconds)).subsec_nanos())
+            };
+            let elapsed = nanos(naive - start);
+            let elapsed = elapsed - elapsed.rem_euclid(nanos(self.duration));
+            start += Duration::seconds((elapsed / 1_000_000_000) as i64)
+                + Duration::nanoseconds((elapsed % 1_000_000_000) as i64);
+        }
+        Ok(timezone.boundary(&(start + self.alignment.offset), *local.offset()))
     }
 }
 
+lazy_static! {
+    /// Buckets that are not calendar months are counted from a Monday, so whole weeks are ISO weeks.
+    static ref WEEK_ORIGIN: NaiveDateTime = NaiveDate::from_ymd_opt(1969, 12, 29)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .unwrap();
+}
+
 impl TryFrom<String> for Increment {
     type Error = error::Error;
     fn try_from(s: String) -> Result<Increment> {
@@ -96,7 +216,7 @@ impl TryFrom<String> for Increment {
                     matches.get(5),
                     matches.get(6),
                 ) {
-                    return Ok(Self::from_parts(
+                    return Self::from_parts(
                         years.as_str().parse()?,
                         months.as_str().parse()?,
                         days.as_str().parse()?,
@@ -104,7 +224,8 @@ impl TryFrom<String> for Increment {
                         minutes.as_str().parse()?,
                         seconds.as_str().parse()?,
                         IncrementType::DateTime,
-                    ));
+                    )
+                    .checked(&s);
                 }
             }
         } else if regex_2.is_match(&s) {
@@ -112,7 +233,7 @@ impl TryFrom<String> for Increment {
                 if let (Some(hours), Some(minutes), Some(seconds)) =
                     (matches.get(1), matches.get(2), matches.get(3))
                 {
-                    return Ok(Self::from_parts(
+                    return Self::from_parts(
                         0,
                         0,
                         0,
@@ -120,7 +241,8 @@ impl TryFrom<String> for Increment {
                         minutes.as_str().parse()?,
                         seconds.as_str().parse()?,
                         IncrementType::Time,
-                    ));
+                    )
+                    .checked(&s);
                 }
             }
         } else if regex_1.is_match(&s) {
@@ -128,7 +250,7 @@ impl TryFrom<String> for Increment {
                 if let (Some(years), Some(months), Some(days)) =
                     (matches.get(1), matches.get(2), matches.get(3))
                 {
-                    return Ok(Self::from_parts(
+                    return Self::from_parts(
                         years.as_str().parse()?,
                         months.as_str().parse()?,
                         days.as_str().parse()?,
@@ -136,17 +258,93 @@ impl TryFrom<String> for Increment {
                         0,
                         0,
                         IncrementType::Date,
-                    ));
+                    )
+                    .checked(&s);
                 }
             }
         }
+        if let Some(captures) = ISO_8601_DURATION_REGEX.captures(&s) {
+            let number = |name: &str| -> Result<f64> {
+                match captures.name(name) {
+                    Some(m) => Ok(m.as_str().replace(',', ".").parse()?),
+                    None => Ok(0.0),
+                }
+            };
+            if captures.iter().skip(1).any(|c| c.is_some()) {
+                let months = number("years")? * 12.0 + number("months")?;
+                let seconds = number("weeks")? * 604_800.0
+                    + number("days")? * 86_400.0
+                    + number("hours")? * 3_600.0
+                    + number("minutes")? * 60.0
+                    + number("seconds")?;
+                return Self::from_duration(months as i64, seconds_to_duration(seconds))
+                    .checked(&s);
+            }
+        }
+        if SHORTHAND_REGEX.is_match(&s) {
+            let mut months = 0;
+            let mut seconds = 0.0;
+            for captures in SHORTHAND_PART_REGEX.captures_iter(&s) {
+                let value: f64 = captures["value"].parse()?;
+                let unit = &captures["unit"];
+                match (unit, value.fract() == 0.0) {
+                    ("mo", true) => months += value as i64,
+                    ("q", true) => months += value as i64 * 3,
+                    ("y", true) => months += value as i64 * 12,
+                    ("mo", false) | ("q", false) | ("y", false) => {
+                        return Err(error::Error {
+                            reason: format!(
+                                "Unable to parse {} into increment, months, quarters and years must be whole numbers",
+                                s
+                            ),
+                            kind: error::ErrorKind::Increment,
+                        })
+                    }
+                    _ => seconds += value * shorthand_seconds(unit),
+                }
+            }
+            return Self::from_duration(months, seconds_to_duration(seconds)).checked(&s);
+        }
         Err(error::Error {
-            reason: format!("Unable to parse {} into increment", s),
+            reason: format!(
+                "Unable to parse {} into increment, use YYYY-MM-DD, HH:MM:SS, YYYY-MM-DD HH:MM:SS, an ISO 8601 duration (P1W, PT15M, PT0.5S) or a shorthand (15m, 1h, 1d, 1w, 1mo)",
+                s
+            ),
             kind: error::ErrorKind::Increment,
         })
     }
 }
 
+lazy_static! {
+    static ref ISO_8601_DURATION_REGEX: Regex = Regex::new(
+        r"^[Pp](?:(?P<years>\d+)Y)?(?:(?P<months>\d+)M)?(?:(?P<weeks>\d+)W)?(?:(?P<days>\d+)D)?(?:T(?:(?P<hours>\d+)H)?(?:(?P<minutes>\d+)M)?(?:(?P<seconds>\d+(?:[.,]\d+)?)S)?)?$"
+    )
+    .unwrap();
+    static ref SHORTHAND_REGEX: Regex =
+        Regex::new(r"^(?:\d+(?:\.\d+)?(?:ns|us|ms|mo|s|m|h|d|w|q|y))+$").unwrap();
+    static ref SHORTHAND_PART_REGEX: Regex =
+        Regex::new(r"(?P<value>\d+(?:\.\d+)?)(?P<unit>ns|us|ms|mo|s|m|h|d|w|q|y)").unwrap();
+}
+
+/// The seconds in a shorthand unit that is not a calendar unit.
+fn shorthand_seconds(unit: &str) -> f64 {
+    match unit {
+        "ns" => 1e-9,
+        "us" => 1e-6,
+        "ms" => 1e-3,
+        "m" => 60.0,
+        "h" => 3_600.0,
+        "d" => 86_400.0,
+        "w" => 604_800.0,
+        _ => 1.0,
+    }
+}
+
+fn seconds_to_duration(seconds: f64) -> Duration {
+    Duration::seconds(seconds.trunc() as i64)
+        + Duration::nanoseconds((seconds.fract() * 1e9).round() as i64)
+}
+
 #[cfg(test)]
 mod tests {
     //
@@ -160,4 +358,81 @@ mod tests {
         let i = Increment::try_from("0001-01-01 02:06:01".to_string());
         println!("{:?}", i);
     }
+    #[test]
+    fn durations_and_shorthand() {
+        let parse = |s: &str| Increment::try_from(s.to_string()).unwrap();
+        assert_eq!(parse("P1W").duration, Duration::weeks(1));
+        assert_eq!(parse("PT15M").duration, Duration::minutes(15));
+        assert_eq!(parse("PT0.5S").duration, Duration::milliseconds(500));
+        assert_eq!(parse("P1Y2M").months, 14);
+        assert_eq!(parse("1h30m").duration, Duration::minutes(90));
+        assert_eq!(parse("1w").duration, Duration::weeks(1));
+        assert_eq!(parse("1mo").months, 1);
+        assert_eq!(parse("1q").months, 3);
+        let err = Increment::try_from("fortnight".to_string()).unwrap_err();
+        assert!(err.reason.contains("PT15M"));
+        assert!(Increment::try_from("PT0S".to_string()).is_err());
+        assert!(Increment::try_from("1.5mo".to_string()).is_err());
+    }
+    #[test]
+    fn aligned_buckets() {
+        let round = |alignment: Alignment, dt: &str| {
+            Increment::try_from("4h".to_string())
+                .unwrap()
+                .align(alignment)
+                .rounded(DateTime::parse_from_rfc3339(dt).unwrap())
+                .unwrap()
+                .to_rfc3339()
+        };
+        let shifts = Alignment {
+            offset: Increment::parse_offset("6h").unwrap(),
+            ..Alignment::default()
+        };
+        assert_eq!(
+            round(shifts, "2021-03-01T05:00:00+00:00"),
+            "2021-03-01T02:00:00+00:00"
+        );
+        // Offsets of the data don't change the bucket
+        assert_eq!(
+            round(shifts, "2021-03-01T07:00:00+02:00"),
+            "2021-03-01T02:00:00+00:00"
+        );
+        let origin = Alignment {
+            timezone: Timezone::new("+01:00").unwrap(),
+            origin: DateTime::parse_from_rfc3339("2021-01-01T07:00:00+01:00").ok(),
+            ..Alignment::default()
+        };
+        assert_eq!(
+            round(origin, "2021-03-01T10:30:00+00:00"),
+            "2021-03-01T11:00:00+01:00"
+        );
+        assert!(Increment::parse_offset("1mo").is_err());
+    }
+    #[test]
+    fn calendar_buckets() {
+        let round = |increment: &str, dt: &str, tz: &str| {
+            Increment::try_from(increment.to_string())
+                .unwrap()
+                .timezone(Timezone::new(tz).unwrap())
+                .rounded(DateTime::parse_from_rfc3339(dt).unwrap())
+                .unwrap()
+                .to_rfc3339()
+        };
+        let dt = "2021-08-19T10:30:00+00:00";
+        assert_eq!(round("0000-01-00", dt, "UTC"), "2021-08-01T00:00:00+00:00");
+        assert_eq!(round("0000-03-00", dt, "UTC"), "2021-07-01T00:00:00+00:00");
+        assert_eq!(round("0001-00-00", dt, "UTC"), "2021-01-01T00:00:00+00:00");
+        // 2021-08-19 is a Thursday in ISO week 33
+        assert_eq!(round("0000-00-07", dt, "UTC"), "2021-08-16T00:00:00+00:00");
+        assert_eq!(round("01:00:00", dt, "+05:30"), "2021-08-19T16:00:00+05:30");
+        // Months start at local midnight, which moves with daylight saving time
+        assert_eq!(
+            round("0000-01-00", dt, "Europe/London"),
+            "2021-08-01T00:00:00+01:00"
+        );
+        assert_eq!(
+            round("0000-01-00", "2021-11-30T23:30:00+00:00", "Europe/London"),
+            "2021-11-01T00:00:00+00:00"
+        );
+    }
 }
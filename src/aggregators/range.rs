@@ -2,35 +2,149 @@
 //!
 //! The RangeAggregator Aggregator component can be used to find all Data objects that fall in a particular date time range.
 //!
+//! Several ranges can be selected at once as [`Window`]s, which can also be read from a CSV or YAML file with `start`, `end` and `name` fields.
+//! Matching CSV records can be tagged with the names of the windows they fall in, the name is written as a field in front of the record and a `window` field is added to the front of the header.
+//! The names are added to any [`tag`](crate::Data::tag) the parser gave the record, after a space.
+//! Records can also be filtered by [`Recurring`] windows such as business hours, a record has to fall in one of the windows and one of the recurring windows when both are used.
+//!
 
-use crate::{aggregators::Aggregator, Data, Result};
-use chrono::{DateTime, FixedOffset};
+use crate::{
+    aggregators::{boundary, recurring::Recurring, Aggregator},
+    Data, Result,
+};
+use chrono::{DateTime, FixedOffset, Utc};
 use std::{
     fs,
     io::{prelude::*, BufReader},
+    path::Path,
 };
 
 // 1 GB limit to Range Aggregator
 const SIZE_LIMIT: usize = 1_000_000;
 const TEMP_SAVE: &str = "/tmp/DTA_RANGE_AGG";
 
+/// A named range, the end is inclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub name: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+}
+
+/// A window as it is written in a ranges file.
+#[derive(Deserialize, Debug)]
+struct WindowEntry {
+    name: Option<String>,
+    start: String,
+    end: Option<String>,
+}
+
+impl Window {
+    /// Create a `Window`, the start and end can be timestamps or [boundary expressions](crate::aggregators::boundary).
+    /// When no name is provided the window is named after its start and end.
+    pub fn new(
+        name: Option<String>,
+        start: &str,
+        end: Option<&str>,
+        tz: Option<&String>,
+    ) -> Result<Self> {
+        let (start_time, end_time) = boundary::resolve(start, end, tz, Utc::now())?;
+        let name = name.unwrap_or_else(|| match end {
+            Some(e) => format!("{}/{}", start, e),
+            None => start.to_string(),
+        });
+        Ok(Self {
+            name,
+            start: start_time,
+            end: end_time,
+        })
+    }
+    /// Read windows from a YAML file (a list of windows) or, for any other extension, a CSV file with a header.
+    pub fn from_file(path: &Path, tz: Option<&String>) -> Result<Vec<Self>> {
+        let entries: Vec<WindowEntry> = match path.extension().and_then(|e| e.to_str()) {
+            Some("yml") | Some("yaml") => serde_yaml::from_reader(fs::File::open(path)?)?,
+            _ => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .from_path(path)?;
+                let mut entries = Vec::new();
+                for entry in reader.deserialize() {
+                    entries.push(entry?);
+                }
+                entries
+            }
+        };
+        entries
+            .into_iter()
+            .map(|e| {
+                let end = e.end.filter(|e| !e.is_empty());
+                Self::new(e.name, &e.start, end.as_deref(), tz)
+            })
+            .collect()
+    }
+    fn contains(&self, timestamp: &DateTime<FixedOffset>) -> bool {
+        *timestamp >= self.start && *timestamp <= self.end
+    }
+}
+
 pub struct RangeAggregator {
-    start: DateTime<FixedOffset>,
-    end: DateTime<FixedOffset>,
+    windows: Vec<Window>,
+    recurring: Vec<Recurring>,
     inverted: bool,
+    tag_windows: bool,
+    delimiter: u8,
+    quote: u8,
     in_range: Vec<Data>,
     current_size: usize,
     data_written_to_file: bool,
+    header: Option<Vec<u8>>,
+    header_pending: bool,
 }
 
 impl Aggregator for RangeAggregator {
     fn update(&mut self, data: &Data) -> Result<()> {
-        if (data.timestamp >= self.start && data.timestamp <= self.end && !self.inverted)
-            || ((data.timestamp <= self.start || data.timestamp >= self.end) && self.inverted)
-        {
-            self.in_range.push(data.clone());
+        let matched: Vec<&str> = self
+            .windows
+            .iter()
+            .filter(|w| w.contains(&data.timestamp))
+            .map(|w| w.name.as_str())
+            .collect();
+        let selected = (self.windows.is_empty() || !matched.is_empty())
+            && (self.recurring.is_empty()
+                || self.recurring.iter().any(|r| r.contains(&data.timestamp)));
+        // Inverted ranges match exactly the records that are not selected
+        if selected != self.inverted {
+            // The header goes before the first entry that follows it
+            if let (Some(header), true) = (&self.header, self.header_pending) {
+                self.header_pending = false;
+                self.current_size += header.len();
+                self.in_range.push(Data {
+                    raw: header.clone(),
+                    ..Data::default()
+                });
+            }
+            let mut data = data.clone();
+            if self.tag_windows && !self.inverted {
+                let name = matched.join(";");
+                // The name is a CSV field in front of the record, matching the `window` field added to the header
+                let (delimiter, quote) = (self.delimiter as char, self.quote as char);
+                let field = match name.contains(&[delimiter, quote, '\n'][..]) {
+                    true => format!(
+                        "{1}{0}{1}",
+                        name.replace(quote, &format!("{0}{0}", quote)),
+                        quote
+                    ),
+                    false => name.clone(),
+                };
+                data.raw = [field.as_bytes(), &[self.delimiter], &data.raw].concat();
+                data.tag = Some(match data.tag.take() {
+                    Some(tag) => format!("{} {}", tag, name),
+                    None => name,
+                });
+            }
             self.current_size += data.raw.len();
             debug!("Added date to store, date {:?}", data.timestamp);
+            self.in_range.push(data);
         }
         if self.current_size > SIZE_LIMIT {
             self.current_size = 0;
@@ -49,39 +163,175 @@ impl Aggregator for RangeAggregator {
     }
     fn return_value(&self) -> Result<String> {
         //
+        if self.data_written_to_file {
+            for line in Self::read_temp_file()? {
+                println!("{}", line);
+            }
+        }
         for data in &self.in_range {
             println!("{}", data.as_string()?.trim_end());
         }
+        Ok("".to_string())
+    }
+    /// Returns the entries matched since the last snapshot, so followed data is only emitted once.
+    fn snapshot(&mut self) -> Result<String> {
+        let mut lines = Vec::new();
         if self.data_written_to_file {
-            let file = fs::OpenOptions::new().read(true).open(TEMP_SAVE)?;
-            let mut reader = BufReader::new(file);
-            let mut line = String::new();
-            while let Ok(0) = reader.read_line(&mut line) {
-                println!("{}", line.trim_end());
-            }
-            fs::remove_file(TEMP_SAVE)?;
+            lines = Self::read_temp_file()?;
+            self.data_written_to_file = false;
         }
-        Ok("".to_string())
+        for data in self.in_range.drain(..) {
+            lines.push(data.as_string()?.trim_end().to_string());
+        }
+        self.current_size = 0;
+        Ok(lines.join("\n"))
+    }
+    /// The header is printed before the first matching entry, and again before the next one when it changes.
+    fn set_header(&mut self, header: Vec<u8>) {
+        self.header = match self.tag_windows && !self.inverted {
+            true => Some([&b"window"[..], &[self.delimiter], &header[..]].concat()),
+            false => Some(header),
+        };
+        self.header_pending = true;
     }
 }
 
 impl RangeAggregator {
-    pub fn new(start: String, end: Option<String>, inverted: bool) -> Result<Self> {
-        let start_time = Data::new(start.as_str(), None, None, start.as_bytes().to_vec())?;
-        let end_time = match end {
-            None => {
-                let date = chrono::Utc::now().to_string();
-                Data::new(date.as_str(), None, None, date.as_bytes().to_vec())?
-            }
-            Some(e) => Data::new(e.as_str(), None, None, e.as_bytes().to_vec())?,
-        };
-        Ok(Self {
-            in_range: Vec::new(),
-            start: start_time.timestamp,
-            end: end_time.timestamp,
+    /// Read back the entries that were moved out of memory, removing the file.
+    fn read_temp_file() -> Result<Vec<String>> {
+        let file = fs::OpenOptions::new().read(true).open(TEMP_SAVE)?;
+        let mut lines = Vec::new();
+        for line in BufReader::new(file).lines() {
+            lines.push(line?.trim_end().to_string());
+        }
+        fs::remove_file(TEMP_SAVE)?;
+        Ok(lines)
+    }
+    /// Create a `RangeAggregator`, the start and end can be timestamps or [boundary expressions](crate::aggregators::boundary) resolved against now.
+    /// Timestamps without a timezone are read in the timezone provided.
+    pub fn new(
+        start: String,
+        end: Option<String>,
+        inverted: bool,
+        tz: Option<&String>,
+    ) -> Result<Self> {
+        let window = Window::new(None, &start, end.as_deref(), tz)?;
+        Ok(Self::from_windows(vec![window], inverted))
+    }
+    /// Create a `RangeAggregator` that matches records in any of the windows, or outside all of them when inverted.
+    /// With no windows every record matches, unless it is filtered by [`RangeAggregator::recurring`].
+    pub fn from_windows(windows: Vec<Window>, inverted: bool) -> Self {
+        debug!("Range windows: {:?}", windows);
+        Self {
+            windows,
+            recurring: Vec::new(),
             inverted,
+            tag_windows: false,
+            delimiter: b',',
+            quote: b'"',
+            in_range: Vec::new(),
             current_size: 0,
             data_written_to_file: false,
-        })
+            header: None,
+            header_pending: false,
+        }
+    }
+    /// Only match records that also fall in one of the recurring windows.
+    pub fn recurring(mut self, recurring: Vec<Recurring>) -> Self {
+        self.recurring = recurring;
+        self
+    }
+    /// Write the names of the windows a record matched as a CSV field before it, this has no effect on inverted ranges.
+    pub fn tag_windows(mut self, tag_windows: bool) -> Self {
+        self.tag_windows = tag_windows;
+        self
+    }
+    /// The CSV delimiter and quote character the window field is written with, a comma and a double quote by default.
+    pub fn csv(mut self, delimiter: u8, quote: u8) -> Self {
+        self.delimiter = delimiter;
+        self.quote = quote;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn match_windows() {
+        let window = |name: &str, start: &str, end: &str| {
+            Window::new(Some(name.to_string()), start, Some(end), None).unwrap()
+        };
+        let windows = vec![
+            window("first", "2021-03-01 00:00:00", "2021-03-01 06:00:00"),
+            window("second", "2021-03-02 00:00:00", "2021-03-02 06:00:00"),
+        ];
+        let data = |s: &str| Data::new(s, None, None, None, s.as_bytes().to_vec()).unwrap();
+        let mut aggregator =
+            RangeAggregator::from_windows(windows.clone(), false).tag_windows(true);
+        for s in &[
+            "2021-03-01 01:00:00",
+            "2021-03-01 12:00:00",
+            "2021-03-02 01:00:00",
+        ] {
+            aggregator.update(&data(s)).unwrap();
+        }
+        assert_eq!(
+            aggregator.snapshot().unwrap(),
+            "first,2021-03-01 01:00:00\nsecond,2021-03-02 01:00:00"
+        );
+        let mut aggregator = RangeAggregator::from_windows(windows.clone(), true);
+        for s in &[
+            "2021-03-01 01:00:00",
+            "2021-03-01 12:00:00",
+            "2021-03-02 01:00:00",
+        ] {
+            aggregator.update(&data(s)).unwrap();
+        }
+        assert_eq!(aggregator.snapshot().unwrap(), "2021-03-01 12:00:00");
+        // Each record is either selected or matched by the inverted range, boundaries included
+        let mut aggregator = RangeAggregator::from_windows(
+            vec![window("a,b", "2021-03-01 00:00:00", "2021-03-01 06:00:00")],
+            false,
+        )
+        .tag_windows(true);
+        aggregator.set_header(b"time".to_vec());
+        aggregator.update(&data("2021-03-01 06:00:00")).unwrap();
+        assert_eq!(
+            aggregator.snapshot().unwrap(),
+            "window,time\n\"a,b\",2021-03-01 06:00:00"
+        );
+        let mut aggregator = RangeAggregator::from_windows(windows.clone(), true);
+        aggregator.update(&data("2021-03-01 06:00:00")).unwrap();
+        assert_eq!(aggregator.snapshot().unwrap(), "");
+        // The tag set by the parser is kept
+        let mut aggregator = RangeAggregator::from_windows(windows, false)
+            .tag_windows(true)
+            .csv(b'|', b'"');
+        aggregator.set_header(b"time|role".to_vec());
+        let mut entry = data("2021-03-01 01:00:00");
+        entry.tag = Some("m".to_string());
+        aggregator.update(&entry).unwrap();
+        assert_eq!(aggregator.in_range[1].tag.as_deref(), Some("m first"));
+        assert_eq!(
+            aggregator.snapshot().unwrap(),
+            "window|time|role\nfirst|2021-03-01 01:00:00"
+        );
+    }
+    #[test]
+    fn header_before_entries_following_it() {
+        let window = Window::new(None, "2021-03-01", None, None).unwrap();
+        let data = |s: &str| Data::new(s, None, None, None, s.as_bytes().to_vec()).unwrap();
+        let mut aggregator = RangeAggregator::from_windows(vec![window], false);
+        aggregator.set_header(b"#Fields: date time".to_vec());
+        aggregator.update(&data("2021-03-01 01:00:00")).unwrap();
+        aggregator.update(&data("2021-03-01 02:00:00")).unwrap();
+        aggregator.set_header(b"#Fields: time date".to_vec());
+        aggregator.update(&data("2021-03-01 03:00:00")).unwrap();
+        assert_eq!(
+            aggregator.snapshot().unwrap(),
+            "#Fields: date time\n2021-03-01 01:00:00\n2021-03-01 02:00:00\n#Fields: time date\n2021-03-01 03:00:00"
+        );
     }
 }
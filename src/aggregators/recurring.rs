@@ -0,0 +1,131 @@
+//! # Recurring Windows
+//!
+//! Recurring windows select times that repeat every week, such as business hours, and are used to filter the [`RangeAggregator`](crate::aggregators::range::RangeAggregator).
+//!
+//! A window is written as days followed by a time of day range, either part can be left out: `mon-fri 09:00-17:00`, `weekends`, `sat,sun` or `22:00-06:00`.
+//! Days are `mon` to `sun`, ranges of days such as `mon-fri`, lists of days or ranges separated by commas, `weekdays`, `weekends` or `daily`.
+//! The end of the time range is not part of the window, a time range that ends before it starts carries on past midnight (into the day after each selected day).
+//!
+
+use crate::{error, Result, Timezone};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, Utc, Weekday};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurring {
+    days: [bool; 7],
+    start: NaiveTime,
+    end: Option<NaiveTime>,
+    timezone: Timezone,
+}
+
+impl Recurring {
+    /// Create a `Recurring` window from its days and time of day, the times are local times in the timezone provided.
+    pub fn new(spec: &str, timezone: Timezone) -> Result<Self> {
+        let invalid = |reason: String| {
+            error::Error {
+            reason: format!(
+                "{} in recurring window \"{}\", use days and a time range such as \"mon-fri 09:00-17:00\"",
+                reason, spec
+            ),
+            kind: error::ErrorKind::Aggregator,
+        }
+        };
+        let mut days = [true; 7];
+        let mut times = None;
+        for part in spec.split_whitespace() {
+            match part.contains(':') {
+                true if times.is_none() => times = Some(part),
+                true => return Err(invalid("More than one time range".to_string())),
+                false => days = parse_days(part).map_err(invalid)?,
+            }
+        }
+        let (start, end) = match times {
+            Some(t) => {
+                let (start, end) = t
+                    .split_once('-')
+                    .ok_or_else(|| invalid(format!("{} is not a time range", t)))?;
+                let time = |s: &str| {
+                    NaiveTime::parse_from_str(s, "%H:%M:%S")
+                        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+                        .map_err(|_| invalid(format!("{} is not a time", s)))
+                };
+                (time(start)?, Some(time(end)?))
+            }
+            None => (NaiveTime::MIN, None),
+        };
+        Ok(Self {
+            days,
+            start,
+            end,
+            timezone,
+        })
+    }
+    /// Check if a timestamp falls in the window.
+    pub fn contains(&self, timestamp: &DateTime<FixedOffset>) -> bool {
+        let local = self.timezone.from_utc(&timestamp.with_timezone(&Utc));
+        let day = local.weekday();
+        let time = local.time();
+        let selected = |d: Weekday| self.days[d.num_days_from_monday() as usize];
+        match self.end {
+            None => selected(day),
+            Some(end) if self.start < end => selected(day) && time >= self.start && time < end,
+            // The window carries on past midnight
+            Some(end) => {
+                (selected(day) && time >= self.start) || (selected(day.pred()) && time < end)
+            }
+        }
+    }
+}
+
+/// Parse the days of a window into a flag for each day starting on Monday.
+fn parse_days(s: &str) -> std::result::Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    for part in s.to_lowercase().split(',').filter(|p| !p.is_empty()) {
+        let (first, last) = match part {
+            "daily" => (Weekday::Mon, Weekday::Sun),
+            "weekdays" => (Weekday::Mon, Weekday::Fri),
+            "weekends" => (Weekday::Sat, Weekday::Sun),
+            p => match p.split_once('-') {
+                Some((f, l)) => (parse_day(f)?, parse_day(l)?),
+                None => (parse_day(p)?, parse_day(p)?),
+            },
+        };
+        // Ranges of days can wrap around the end of the week, such as fri-mon
+        let mut day = first;
+        loop {
+            days[day.num_days_from_monday() as usize] = true;
+            if day == last {
+                break;
+            }
+            day = day.succ();
+        }
+    }
+    Ok(days)
+}
+
+fn parse_day(s: &str) -> std::result::Result<Weekday, String> {
+    s.parse::<Weekday>()
+        .map_err(|_| format!("{} is not a day", s))
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn business_hours_and_weekends() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+        let london = Timezone::new("Europe/London").unwrap();
+        let business = Recurring::new("mon-fri 09:00-17:00", london).unwrap();
+        // 2021-07-05 is a Monday, London is an hour ahead of UTC in summer
+        assert!(business.contains(&at("2021-07-05T08:30:00+00:00")));
+        assert!(!business.contains(&at("2021-07-05T16:30:00+00:00")));
+        assert!(!business.contains(&at("2021-07-04T10:00:00+00:00")));
+        let weekends = Recurring::new("sat,sun", Timezone::default()).unwrap();
+        assert!(weekends.contains(&at("2021-07-04T10:00:00+00:00")));
+        let nights = Recurring::new("fri 22:00-06:00", Timezone::default()).unwrap();
+        assert!(nights.contains(&at("2021-07-10T05:00:00+00:00")));
+        assert!(!nights.contains(&at("2021-07-09T05:00:00+00:00")));
+        assert!(Recurring::new("someday 09:00-17:00", Timezone::default()).is_err());
+    }
+}
@@ -5,25 +5,30 @@
 //! The increment option of the [`SplitAggregator::new()`](SplitAggregator::new()) function accepts a string of any case matching the above options.
 //! [`SplitAggregator::new()`](SplitAggregator::new()) also accepts and option to flatten the resulting data so data with a timestamp of 2021-01-01 01:00:00 with a split increment of "month" will be saved to a file called "./output_directory/01_dta".
 use crate::{aggregators::Aggregator, Data, Result};
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+const ORIGIN_PLACEHOLDER: &str = "{origin}";
 
 pub struct SplitAggregator {
     output_directory: PathBuf,
     filename: String,
     created_files: Vec<PathBuf>,
+    header: Option<Vec<u8>>,
+    /// The header last written to each file.
+    file_headers: HashMap<PathBuf, Vec<u8>>,
 }
 
 impl Aggregator for SplitAggregator {
     fn update(&mut self, data: &Data) -> Result<()> {
-        let path = self
-            .output_directory
-            .join(data.timestamp.format(&self.filename).to_string());
+        let path = self.output_directory.join(self.filename_for(data));
         self.created_files.push(path.clone());
         self.created_files.dedup();
-        if self.filename.contains('/') {
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(&parent)?;
-            }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
         let mut file = fs::OpenOptions::new()
             .create(true)
@@ -32,18 +37,19 @@ impl Aggregator for SplitAggregator {
             .open(&path)?;
         // Write to file
         use std::io::Write;
+        if let Some(header) = &self.header {
+            // Files that already have data get the header again when it has changed since
+            let written = self.file_headers.get(&path);
+            if written != Some(header) && (written.is_some() || file.metadata()?.len() == 0) {
+                file.write_all(header)?;
+                file.write_all(b"\n")?;
+                self.file_headers.insert(path.clone(), header.clone());
+            }
+        }
         let len = file.write(&data.raw)?;
         let _ = file.write(b"\n")?;
         //
-        debug!(
-            "Written {} bytes to {}/{}",
-            len,
-            self.output_directory.display(),
-            &self
-                .output_directory
-                .join(data.timestamp.format(&self.filename).to_string())
-                .display()
-        );
+        debug!("Written {} bytes to {}", len, path.display());
         Ok(())
     }
     fn return_value(&self) -> Result<String> {
@@ -59,6 +65,14 @@ impl Aggregator for SplitAggregator {
             self.created_files
         ))
     }
+    /// Data is written out as it arrives so there is nothing to report until the split completes.
+    fn snapshot(&mut self) -> Result<String> {
+        Ok(String::new())
+    }
+    /// The header is written at the start of every file created by the split, and again before the next entry of a file when it changes.
+    fn set_header(&mut self, header: Vec<u8>) {
+        self.header = Some(header);
+    }
 }
 
 impl SplitAggregator {
@@ -71,9 +85,32 @@ impl SplitAggregator {
             output_directory,
             filename,
             created_files: Vec::new(),
+            header: None,
+            file_headers: HashMap::new(),
         })
     }
 
+    /// Build the filename for a `Data` entry, formatting the timestamp and substituting its origin.
+    fn filename_for(&self, data: &Data) -> String {
+        let filename = data.timestamp.format(&self.filename).to_string();
+        if !filename.contains(ORIGIN_PLACEHOLDER) {
+            return filename;
+        }
+        // Only keep the normal components of the origin so it can't escape the output directory
+        let origin = match &data.origin {
+            Some(o) => Path::new(o)
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(n) => Some(n.to_string_lossy().into_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<String>>()
+                .join("/"),
+            None => "unknown".to_string(),
+        };
+        filename.replace(ORIGIN_PLACEHOLDER, &origin)
+    }
+
     // /// Return the output of the aggregation
     // pub fn output(&self) -> Result<()> {
     //     // debug!("Maximum Aggregator returning output: {:?}", self.largest);
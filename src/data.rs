@@ -1,21 +1,34 @@
 use crate::error::{self, Result};
 use chrono::{DateTime, FixedOffset, NaiveDateTime};
-use std::{collections::HashMap, fmt, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Clone)]
 pub struct Data {
     pub timestamp: DateTime<FixedOffset>,
     pub raw: Vec<u8>,
+    /// Where the data was read from (a file path or archive member), if the source provides one.
+    pub origin: Option<String>,
+    /// A label for entries that are one of several read from the same raw data, such as the MACB role of a bodyfile timestamp.
+    pub tag: Option<String>,
+    /// The timestamp was missing parts (such as the year) that were inferred, see [`Completion`](parsing::Completion).
+    pub inferred: bool,
 }
 
+use parsing::utc_offset;
+
 impl Default for Data {
     fn default() -> Self {
         Self {
-            timestamp: DateTime::from_utc(
-                NaiveDateTime::from_timestamp(0, 0),
-                chrono::offset::FixedOffset::east(0),
-            ),
+            timestamp: DateTime::from_naive_utc_and_offset(NaiveDateTime::default(), utc_offset()),
             raw: Vec::new(),
+            origin: None,
+            tag: None,
+            inferred: false,
         }
     }
 }
@@ -24,35 +37,62 @@ impl Data {
     pub fn from_dict(
         s: &str,
         raw: Vec<u8>,
+        epoch: Option<parsing::EpochKind>,
         tz: Option<&String>,
         dictionary: &mut parsing::FormatDictionary,
     ) -> Result<Self> {
+        let (timestamp, inferred) = dictionary.parse_completed(s, epoch, tz)?;
         Ok(Self {
-            timestamp: dictionary.parse_datetime(s, tz)?,
+            timestamp,
             raw,
+            origin: None,
+            tag: None,
+            inferred,
         })
     }
-    pub fn new(s: &str, f: Option<&String>, tz: Option<&String>, raw: Vec<u8>) -> Result<Self> {
+    /// Parse a timestamp with the datetime format `f`, numeric timestamps are read as the `epoch` provided or the one detected from their magnitude.
+    pub fn new(
+        s: &str,
+        f: Option<&String>,
+        epoch: Option<parsing::EpochKind>,
+        tz: Option<&String>,
+        raw: Vec<u8>,
+    ) -> Result<Self> {
         // Parse timestamp
         if let Some(timestamp) = parsing::parse_dt(s, f) {
-            return Ok(Data { timestamp, raw });
+            return Ok(Data {
+                timestamp,
+                raw,
+                origin: None,
+                tag: None,
+                inferred: false,
+            });
         }
         if let Some(timestamp) = parsing::parse_naive_dt(s, f, tz) {
-            return Ok(Data { timestamp, raw });
+            return Ok(Data {
+                timestamp,
+                raw,
+                origin: None,
+                tag: None,
+                inferred: false,
+            });
         }
-        if let Ok(epoch) = s.parse() {
-            if let Some(timestamp) = parsing::parse_integer(epoch, 0, tz) {
-                return Ok(Data { timestamp, raw });
-            }
+        if let Some(timestamp) = parsing::parse_epoch(s, epoch, tz) {
+            return Ok(Data {
+                timestamp,
+                raw,
+                origin: None,
+                tag: None,
+                inferred: false,
+            });
         }
         Err(crate::error::Error {
             reason: format!(
                 "{} cannot be parsed. Format string provided {} is not valid.",
                 s,
-                if f.is_none() {
-                    String::new()
-                } else {
-                    format!("({}) ", f.unwrap())
+                match f {
+                    Some(f) => format!("({}) ", f),
+                    None => String::new(),
                 }
             ),
             kind: crate::error::ErrorKind::DateTime,
@@ -77,75 +117,786 @@ pub mod parsing {
     //
     use super::*;
     //
-    #[derive(Deserialize, Debug)]
+    /// The formats bundled with dta, see [`FormatDictionary::bundled`].
+    const BUNDLED_FORMATS: &str = include_str!("../assets/default_formats.yml");
+
+    /// Named formats that timestamps are read with, the format that matched most recently is tried first.
+    /// Timestamps matching none of the formats are parsed as they would be without a dictionary.
+    ///
+    /// Each entry has a `fmt` and can also declare a `tz` that its timestamps are in (used in place of the timezone provided),
+    /// an `epoch` (see [`EpochKind`]) to read numeric timestamps in place of a format, or a `locale` (`de`, `es`, `fr`, `it`, `nl` or `pt`) that month and weekday names are written in.
+    #[derive(Deserialize, Debug, Default)]
     pub struct FormatDictionary {
         #[serde(flatten)]
         inner: HashMap<String, FormatDictionaryInner>,
         #[serde(skip)]
-        priority: Vec<String>,
+        priority: Priority,
+        #[serde(skip)]
+        completion: Option<Completion>,
+        #[serde(skip)]
+        samples: Option<Vec<String>>,
+        #[serde(skip)]
+        date_order: Option<DateOrderResolver>,
+        #[serde(skip)]
+        preferred: Option<String>,
+        #[serde(skip)]
+        ambiguous: AmbiguousTime,
     }
-    #[derive(Deserialize, Debug)]
+    #[derive(Deserialize, Debug, Default)]
     struct FormatDictionaryInner {
+        #[serde(default)]
         fmt: String,
+        tz: Option<String>,
+        epoch: Option<String>,
+        locale: Option<String>,
     }
 
     impl FormatDictionaryInner {
         pub fn get_fmt(&self) -> &str {
             &self.fmt
         }
+        fn validate(&self, name: &str) -> Result<()> {
+            let invalid = |reason: String| {
+                Err(error::Error {
+                    reason: format!("Format dictionary entry {}: {}", name, reason),
+                    kind: error::ErrorKind::Parser,
+                })
+            };
+            if let Some(Err(e)) = self.tz.as_deref().map(Timezone::new) {
+                return invalid(e.reason);
+            }
+            match (&self.epoch, &self.locale) {
+                (Some(e), _) if EpochKind::from_name(e).is_none() => invalid(format!(
+                    "\"{}\" is not an epoch, epochs available: {}",
+                    e,
+                    EpochKind::NAMES.join(", ")
+                )),
+                (_, Some(l)) if locale_names(l).is_none() => {
+                    invalid(format!("\"{}\" is not a locale with month names", l))
+                }
+                (None, _) if self.fmt.is_empty() => invalid("no fmt or epoch".to_string()),
+                (None, _) if StrftimeItems::new(&self.fmt).any(|i| i == Item::Error) => {
+                    invalid(format!("\"{}\" is not a valid format", self.fmt))
+                }
+                _ => Ok(()),
+            }
+        }
+        /// Read a timestamp with the entry, `None` when it does not match.
+        fn parse(
+            &self,
+            s: &str,
+            tz: Option<&String>,
+            ambiguous: AmbiguousTime,
+        ) -> Option<Result<DateTime<FixedOffset>>> {
+            let tz = self.tz.as_ref().or(tz);
+            if let Some(kind) = self.epoch.as_deref().and_then(EpochKind::from_name) {
+                return parse_epoch(s, Some(kind), tz).map(Ok);
+            }
+            let delocalized;
+            let s = match self.locale.as_deref().and_then(|l| delocalize(s, l)) {
+                Some(d) => {
+                    delocalized = d;
+                    delocalized.as_str()
+                }
+                None => s,
+            };
+            if let Ok(dt) = DateTime::parse_from_str(s, &self.fmt) {
+                Some(Ok(dt))
+            } else if let Ok(dt) = NaiveDateTime::parse_from_str(s, &self.fmt) {
+                Some(local_timezone(tz).from_local_as(&dt, ambiguous))
+            } else {
+                None
+            }
+        }
     }
 
     impl FormatDictionary {
+        /// Read a dictionary from a YAML file, or from every YAML file (`.yml` or `.yaml`) in a directory.
+        /// Files in a directory are read in name order, entries override entries with the same name from earlier files.
         pub fn from_file(file: PathBuf) -> Result<Self> {
+            if file.is_dir() {
+                let mut paths: Vec<PathBuf> = fs::read_dir(&file)?
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| {
+                        matches!(
+                            p.extension().and_then(|e| e.to_str()),
+                            Some("yml") | Some("yaml")
+                        )
+                    })
+                    .collect();
+                paths.sort();
+                return paths.into_iter().try_fold(Self::default(), |dict, p| {
+                    Ok(dict.merge(Self::from_file(p)?))
+                });
+            }
             //
             let file = fs::File::open(file)?;
             let dict: Self = serde_yaml::from_reader(file)?;
             //
+            dict.validate()?;
             Ok(dict)
         }
+        /// The formats bundled with dta (`assets/default_formats.yml`).
+        pub fn bundled() -> Result<Self> {
+            let dict: Self = serde_yaml::from_str(BUNDLED_FORMATS)?;
+            dict.validate()?;
+            Ok(dict)
+        }
+        /// Add the entries of another dictionary, they override entries with the same name.
+        pub fn merge(mut self, other: Self) -> Self {
+            self.inner.extend(other.inner);
+            self
+        }
+        fn validate(&self) -> Result<()> {
+            for (name, entry) in self.inner.iter() {
+                entry.validate(name)?;
+            }
+            Ok(())
+        }
+        /// Add a format to the dictionary.
+        pub fn insert(&mut self, name: &str, fmt: &str) {
+            self.inner.insert(
+                name.to_string(),
+                FormatDictionaryInner {
+                    fmt: fmt.to_string(),
+                    ..FormatDictionaryInner::default()
+                },
+            );
+        }
+        /// Add a format that is always tried before the other entries, such as the date format provided on the command line.
+        pub fn prefer(mut self, name: &str, fmt: &str) -> Self {
+            self.insert(name, fmt);
+            self.preferred = Some(name.to_string());
+            self
+        }
+        /// Read local times that happen twice (when clocks go back) this way, the earliest is used otherwise.
+        pub fn read_ambiguous(mut self, ambiguous: AmbiguousTime) -> Self {
+            self.ambiguous = ambiguous;
+            self
+        }
+        /// The way local times that happen twice are read.
+        pub fn ambiguous_time(&self) -> AmbiguousTime {
+            self.ambiguous
+        }
+        /// Complete timestamps that are missing their year or date, see [`Completion`].
+        pub fn complete(mut self, anchor: Option<DateTime<FixedOffset>>) -> Self {
+            self.completion = Some(Completion::new(anchor));
+            self
+        }
+        /// Read dates such as `01/02/2021` in the order provided, otherwise in the order found across the input, see [`DateOrderResolver`].
+        pub fn resolve_date_order(mut self, order: Option<DateOrder>) -> Self {
+            self.date_order = Some(DateOrderResolver::new(order));
+            self
+        }
+        /// Read dates that could be either order as `order` from now on, unless the order has already been provided or found.
+        pub fn assume_date_order(&mut self, order: DateOrder) {
+            if let Some(r) = self.date_order.as_mut() {
+                r.order.get_or_insert(order);
+            }
+        }
+        /// The order of the day and month, once it is provided or found.
+        pub fn date_order(&self) -> Option<DateOrder> {
+            self.date_order.as_ref().and_then(|r| r.order)
+        }
+        /// Collect the timestamps passed to the dictionary instead of parsing them, they are read back with [`FormatDictionary::samples`].
+        pub fn sample(mut self) -> Self {
+            self.samples = Some(Vec::new());
+            self
+        }
+        /// The timestamps collected since [`FormatDictionary::sample`] was called.
+        pub fn samples(&self) -> &[String] {
+            self.samples.as_deref().unwrap_or_default()
+        }
+        /// The name and format of each entry with a format, sorted by name.
+        pub fn formats(&self) -> Vec<(&str, &str)> {
+            let mut formats: Vec<(&str, &str)> = self
+                .inner
+                .iter()
+                .filter(|(_, v)| !v.fmt.is_empty())
+                .map(|(k, v)| (k.as_str(), v.get_fmt()))
+                .collect();
+            formats.sort_unstable();
+            formats
+        }
+        /// Called when the data being read moves on to a new file, so dates can be taken from its name.
+        pub fn set_origin(&mut self, origin: Option<&str>) {
+            if let Some(c) = self.completion.as_mut() {
+                c.set_origin(origin);
+            }
+        }
         pub fn parse_datetime(
             &mut self,
             s: &str,
             tz: Option<&String>,
         ) -> Result<DateTime<FixedOffset>> {
+            Ok(self.parse_completed(s, None, tz)?.0)
+        }
+        /// Parse a timestamp, along with whether it had to be completed.
+        /// Numeric timestamps are read as the `epoch` provided before any entry is tried.
+        pub fn parse_completed(
+            &mut self,
+            s: &str,
+            epoch: Option<EpochKind>,
+            tz: Option<&String>,
+        ) -> Result<(DateTime<FixedOffset>, bool)> {
+            if let Some(samples) = self.samples.as_mut() {
+                samples.push(s.trim().to_string());
+                return Err(error::Error {
+                    reason: format!("Sampled {}", s),
+                    kind: error::ErrorKind::Skipped,
+                });
+            }
+            let preferred = self.parse_preferred(s, tz).transpose()?;
+            // Day and month dates are only read with the entries and formats of their order
+            let order = match (preferred, self.date_order.as_mut()) {
+                (None, Some(r)) => r.resolve(s).transpose()?,
+                _ => None,
+            };
+            let parsed = match preferred.or_else(|| epoch.and_then(|k| parse_epoch(s, Some(k), tz)))
+            {
+                Some(dt) => Some(dt),
+                None => match self.parse_formats(s, tz, order) {
+                    Some(dt) => Some(dt?),
+                    None => order
+                        .and_then(|o| DateOrderResolver::parse(s, o, tz, self.ambiguous))
+                        .or_else(|| parse_dt(s, None))
+                        .or_else(|| parse_naive_dt_as(s, None, tz, self.ambiguous))
+                        .or_else(|| parse_epoch(s, epoch, tz)),
+                },
+            };
+            let formats: Vec<&str> = self
+                .inner
+                .values()
+                .filter(|f| !f.fmt.is_empty())
+                .map(|f| f.get_fmt())
+                .collect();
+            match (parsed, self.completion.as_mut()) {
+                (Some(dt), Some(c)) => {
+                    c.record(&dt, tz);
+                    Ok((dt, false))
+                }
+                (Some(dt), None) => Ok((dt, false)),
+                (None, Some(c)) => match c.complete(s, &formats, tz, self.ambiguous) {
+                    Some(dt) => {
+                        debug!("Completed {} as {}", s, dt);
+                        Ok((dt, true))
+                    }
+                    None => Err(error::Error {
+                        reason: format!("Unable to find format to parse or complete {}", s),
+                        kind: error::ErrorKind::DateTime,
+                    }),
+                },
+                (None, None) => Err(error::Error {
+                    reason: format!("Unable to find format to parse {}", s),
+                    kind: error::ErrorKind::DateTime,
+                }),
+            }
+        }
+        /// Read the priority learned by an earlier run, entries that are no longer in the dictionary are dropped.
+        pub fn load_priority(mut self, file: &Path) -> Result<Self> {
+            let mut priority: Priority = serde_yaml::from_reader(fs::File::open(file)?)?;
+            priority.order.retain(|n| self.inner.contains_key(n));
+            priority.order.truncate(PRIORITY_LIMIT);
+            priority.hits.retain(|n, _| self.inner.contains_key(n));
+            self.priority = priority;
+            Ok(self)
+        }
+        /// Write the learned priority, so later runs start with the formats that matched most recently.
+        pub fn save_priority(&self, file: &Path) -> Result<()> {
+            serde_yaml::to_writer(fs::File::create(file)?, &self.priority)?;
+            Ok(())
+        }
+        /// The number of timestamps each entry has matched, the most matches first.
+        pub fn hits(&self) -> Vec<(&str, u64)> {
+            let mut hits: Vec<(&str, u64)> = self
+                .priority
+                .hits
+                .iter()
+                .map(|(n, h)| (n.as_str(), *h))
+                .collect();
+            hits.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            hits
+        }
+        fn parse_formats(
+            &mut self,
+            s: &str,
+            tz: Option<&String>,
+            order: Option<DateOrder>,
+        ) -> Option<Result<DateTime<FixedOffset>>> {
             // Accept datetime string and using the formats provided by the dictionary parse out a DateTime<FixedOffset>
-            // Read fmts from priority list first
-            for name in self.priority.iter() {
-                if let Some(fmt) = self.inner.get(name) {
-                    if let Ok(dt) = DateTime::parse_from_str(s, fmt.get_fmt()) {
-                        //
-                        return Ok(dt);
-                    } else if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt.get_fmt()) {
-                        return Ok(DateTime::from_utc(
-                            dt,
-                            match parse_fixed_offset(tz) {
-                                Ok(t) => t,
-                                Err(_) => FixedOffset::east(0),
-                            },
-                        ));
+            // Read fmts from priority list first, then the rest of the map by name, the preferred fmt has already been tried
+            let preferred = self.preferred.as_ref();
+            let mut rest: Vec<&String> = self
+                .inner
+                .keys()
+                .filter(|n| !self.priority.order.contains(n))
+                .collect();
+            rest.sort_unstable();
+            let (name, dt) = self
+                .priority
+                .order
+                .iter()
+                .chain(rest)
+                .filter(|n| Some(*n) != preferred)
+                .find_map(|name| {
+                    let entry = self.inner.get(name)?;
+                    // Entries written for the other day and month order are skipped
+                    match (order, DateOrder::of_format(&entry.fmt)) {
+                        (Some(o), Some(f)) if o != f => None,
+                        _ => Some((name.clone(), entry.parse(s, tz, self.ambiguous)?)),
                     }
+                })?;
+            // Matched. Now update priority list
+            self.priority.hit(&name);
+            Some(dt)
+        }
+        /// Read a timestamp with the preferred entry, whatever the order of the day and month.
+        fn parse_preferred(
+            &mut self,
+            s: &str,
+            tz: Option<&String>,
+        ) -> Option<Result<DateTime<FixedOffset>>> {
+            let name = self.preferred.as_ref()?;
+            let dt = self.inner.get(name)?.parse(s, tz, self.ambiguous)?;
+            self.priority.hit(&name.clone());
+            Some(dt)
+        }
+    }
+
+    /// Month names (January first) then weekday names (Monday first) of each locale, alternatives are separated by `|`.
+    const LOCALES: [(&str, [&str; 19]); 6] = [
+        (
+            "de",
+            [
+                "januar|jan|jänner",
+                "februar|feb",
+                "märz|mär|mrz",
+                "april|apr",
+                "mai",
+                "juni|jun",
+                "juli|jul",
+                "august|aug",
+                "september|sep|sept",
+                "oktober|okt",
+                "november|nov",
+                "dezember|dez",
+                "montag",
+                "dienstag",
+                "mittwoch",
+                "donnerstag",
+                "freitag",
+                "samstag|sonnabend",
+                "sonntag",
+            ],
+        ),
+        (
+            "es",
+            [
+                "enero|ene",
+                "febrero|feb",
+                "marzo|mar",
+                "abril|abr",
+                "mayo|may",
+                "junio|jun",
+                "julio|jul",
+                "agosto|ago",
+                "septiembre|setiembre|sep|sept",
+                "octubre|oct",
+                "noviembre|nov",
+                "diciembre|dic",
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+        ),
+        (
+            "fr",
+            [
+                "janvier|janv",
+                "février|févr|fevrier|fevr",
+                "mars",
+                "avril|avr",
+                "mai",
+                "juin",
+                "juillet|juil",
+                "août|aout",
+                "septembre|sept",
+                "octobre|oct",
+                "novembre|nov",
+                "décembre|déc|decembre|dec",
+                "lundi",
+                "mardi",
+                "mercredi",
+                "jeudi",
+                "vendredi",
+                "samedi",
+                "dimanche",
+            ],
+        ),
+        (
+            "it",
+            [
+                "gennaio|gen",
+                "febbraio|feb",
+                "marzo|mar",
+                "aprile|apr",
+                "maggio|mag",
+                "giugno|giu",
+                "luglio|lug",
+                "agosto|ago",
+                "settembre|set",
+                "ottobre|ott",
+                "novembre|nov",
+                "dicembre|dic",
+                "lunedì",
+                "martedì",
+                "mercoledì",
+                "giovedì",
+                "venerdì",
+                "sabato",
+                "domenica",
+            ],
+        ),
+        (
+            "nl",
+            [
+                "januari|jan",
+                "februari|feb",
+                "maart|mrt",
+                "april|apr",
+                "mei",
+                "juni|jun",
+                "juli|jul",
+                "augustus|aug",
+                "september|sep",
+                "oktober|okt",
+                "november|nov",
+                "december|dec",
+                "maandag",
+                "dinsdag",
+                "woensdag",
+                "donderdag",
+                "vrijdag",
+                "zaterdag",
+                "zondag",
+            ],
+        ),
+        (
+            "pt",
+            [
+                "janeiro|jan",
+                "fevereiro|fev",
+                "março|mar",
+                "abril|abr",
+                "maio|mai",
+                "junho|jun",
+                "julho|jul",
+                "agosto|ago",
+                "setembro|set",
+                "outubro|out",
+                "novembro|nov",
+                "dezembro|dez",
+                "segunda-feira",
+                "terça-feira",
+                "quarta-feira",
+                "quinta-feira",
+                "sexta-feira",
+                "sábado",
+                "domingo",
+            ],
+        ),
+    ];
+
+    /// The English names chrono reads, in the same order as the names of [`LOCALES`].
+    const ENGLISH_NAMES: [&str; 19] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec", "Mon",
+        "Tue", "Wed", "Thu", "Fri", "Sat", "Sun",
+    ];
+
+    lazy_static! {
+        static ref NAME_REGEX: regex::Regex = regex::Regex::new(r"[\p{L}-]+").unwrap();
+    }
+
+    /// The names of a locale such as `de`, `de_DE` or `de-DE`.
+    fn locale_names(locale: &str) -> Option<&'static [&'static str; 19]> {
+        let language = locale.get(..2)?.to_lowercase();
+        LOCALES
+            .iter()
+            .find(|(l, _)| *l == language)
+            .map(|(_, names)| names)
+    }
+
+    /// Replace the month and weekday names of a locale with English names.
+    fn delocalize(s: &str, locale: &str) -> Option<String> {
+        let names = locale_names(locale)?;
+        Some(
+            NAME_REGEX
+                .replace_all(s, |c: &regex::Captures| {
+                    let word = c[0].to_lowercase();
+                    names
+                        .iter()
+                        .position(|n| n.split('|').any(|n| n == word))
+                        .map(|i| ENGLISH_NAMES[i].to_string())
+                        .unwrap_or_else(|| c[0].to_string())
+                })
+                .to_string(),
+        )
+    }
+
+    /// The most entries kept in the priority list.
+    const PRIORITY_LIMIT: usize = 8;
+
+    /// The entries of a dictionary that matched most recently, along with the number of timestamps each entry has matched.
+    #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+    struct Priority {
+        order: Vec<String>,
+        hits: HashMap<String, u64>,
+    }
+
+    impl Priority {
+        /// Move an entry to the front, the least recently matched entry is dropped when the list is full.
+        fn hit(&mut self, name: &str) {
+            *self.hits.entry(name.to_string()).or_insert(0) += 1;
+            if self.order.first().map(|n| n.as_str()) == Some(name) {
+                return;
+            }
+            self.order.retain(|n| n != name);
+            self.order.insert(0, name.to_string());
+            self.order.truncate(PRIORITY_LIMIT);
+        }
+    }
+
+    lazy_static! {
+        static ref FILE_DATE_REGEX: regex::Regex =
+            regex::Regex::new(r"(?:^|\D)(\d{4})[-_.]?(\d{2})[-_.]?(\d{2})(?:\D|$)").unwrap();
+    }
+
+    /// Formats of timestamps missing their year or date, tried after the formats of a dictionary.
+    const PARTIAL_FORMATS: [&str; 6] = [
+        "%m-%d %H:%M:%S%.f",
+        "%m/%d %H:%M:%S%.f",
+        "%b %d %H:%M:%S%.f",
+        "%H:%M:%S%.f",
+        "%b %d",
+        "%m-%d",
+    ];
+
+    /// Completes timestamps that are missing their year (`03-14 10:22:01`, `Mar 14`) or their date (`10:22:01.123`).
+    ///
+    /// The missing parts are taken from the last timestamp read from the same file, otherwise a date in the file name (such as `app-2021-03-14.log`), otherwise the anchor.
+    /// Following the last timestamp, the year moves on when a timestamp would be over six months earlier and the day moves on when a time would be over twelve hours earlier.
+    /// Starting from a file name or anchor, the year is chosen so the timestamp is not after it.
+    #[derive(Debug, Clone, Default)]
+    pub struct Completion {
+        anchor: Option<DateTime<FixedOffset>>,
+        file_date: Option<NaiveDate>,
+        last: Option<NaiveDateTime>,
+    }
+
+    impl Completion {
+        pub fn new(anchor: Option<DateTime<FixedOffset>>) -> Self {
+            Self {
+                anchor,
+                ..Self::default()
+            }
+        }
+        /// Take the date from the name of a new file, the last timestamp belongs to the previous file.
+        pub fn set_origin(&mut self, origin: Option<&str>) {
+            self.last = None;
+            self.file_date = origin
+                .and_then(|o| Path::new(o).file_name())
+                .and_then(|n| {
+                    FILE_DATE_REGEX.captures(&n.to_string_lossy()).map(|c| {
+                        NaiveDate::parse_from_str(
+                            &format!("{}-{}-{}", &c[1], &c[2], &c[3]),
+                            "%Y-%m-%d",
+                        )
+                    })
+                })
+                .and_then(|d| d.ok());
+        }
+        /// Record a timestamp with a full date, later timestamps are completed from it.
+        pub fn record(&mut self, dt: &DateTime<FixedOffset>, tz: Option<&String>) {
+            self.last = Some(
+                local_timezone(tz)
+                    .from_utc(&dt.with_timezone(&Utc))
+                    .naive_local(),
+            );
+        }
+        /// Complete a timestamp using the first format it matches.
+        pub fn complete(
+            &mut self,
+            s: &str,
+            formats: &[&str],
+            tz: Option<&String>,
+            ambiguous: AmbiguousTime,
+        ) -> Option<DateTime<FixedOffset>> {
+            let timezone = local_timezone(tz);
+            let (reference, following) = match (self.last, self.file_date, self.anchor) {
+                (Some(l), _, _) => (l, true),
+                (None, Some(d), _) => (d.and_time(NaiveTime::MIN), false),
+                (None, None, Some(a)) => (
+                    timezone.from_utc(&a.with_timezone(&Utc)).naive_local(),
+                    false,
+                ),
+                (None, None, None) => return None,
+            };
+            for fmt in formats.iter().chain(PARTIAL_FORMATS.iter()) {
+                // Try the year first, parsing a time alone ignores any month and day
+                let year_fmt = format!("%Y {}", fmt);
+                let with_year = |year: i32| {
+                    let s = format!("{} {}", year, s);
+                    NaiveDateTime::parse_from_str(&s, &year_fmt).or_else(|_| {
+                        NaiveDate::parse_from_str(&s, &year_fmt).map(|d| d.and_time(NaiveTime::MIN))
+                    })
+                };
+                let completed = match with_year(reference.year()) {
+                    Ok(dt) if following && dt < reference - Duration::days(183) => {
+                        with_year(reference.year() + 1).ok()
+                    }
+                    Ok(dt) if !following && dt > reference + Duration::days(1) => {
+                        with_year(reference.year() - 1).ok()
+                    }
+                    Ok(dt) => Some(dt),
+                    Err(_) => match NaiveTime::parse_from_str(s, fmt) {
+                        Ok(t) => {
+                            let dt = reference.date().and_time(t);
+                            match following && dt < reference - Duration::hours(12) {
+                                true => Some(dt + Duration::days(1)),
+                                false => Some(dt),
+                            }
+                        }
+                        Err(_) => None,
+                    },
+                };
+                if let Some(dt) = completed {
+                    self.last = Some(dt);
+                    return timezone.from_local_as(&dt, ambiguous).ok();
                 }
             }
-            // If no matches from priority list read from map
-            for (name, fmt) in self.inner.iter().map(|(k, v)| (k, v.get_fmt())) {
-                if let Ok(dt) = DateTime::parse_from_str(s, fmt) {
-                    // Matched. Now update priority list
-                    self.priority.insert(0, name.to_string());
-                    return Ok(dt);
-                } else if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
-                    return Ok(DateTime::from_utc(
-                        dt,
-                        match parse_fixed_offset(tz) {
-                            Ok(t) => t,
-                            Err(_) => FixedOffset::east(0),
-                        },
-                    ));
+            None
+        }
+    }
+
+    lazy_static! {
+        static ref DAY_MONTH_REGEX: regex::Regex =
+            regex::Regex::new(r"^(\d{1,2})([/.])(\d{1,2})[/.]\d{4}\b").unwrap();
+    }
+
+    /// The times tried after a day and month date when no entry of the dictionary reads it, with and without a zone.
+    const DAY_MONTH_TIMES: [&str; 4] = ["%H:%M:%S%.f", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+    /// The order of the day and month in dates such as `01/02/2021`.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DateOrder {
+        /// `DD/MM/YYYY`
+        DayFirst,
+        /// `MM/DD/YYYY`
+        MonthFirst,
+    }
+
+    impl DateOrder {
+        /// Read `dmy` or `mdy`, `auto` leaves the order to be found from the input.
+        pub fn new(s: &str) -> Result<Option<Self>> {
+            match s.to_lowercase().as_str() {
+                "auto" => Ok(None),
+                "dmy" => Ok(Some(DateOrder::DayFirst)),
+                "mdy" => Ok(Some(DateOrder::MonthFirst)),
+                _ => Err(error::Error {
+                    reason: format!(
+                        "Unknown date order \"{}\", orders available: auto, dmy, mdy",
+                        s
+                    ),
+                    kind: error::ErrorKind::DateTime,
+                }),
+            }
+        }
+        /// The order of the day and month of a format such as `%d/%m/%Y`, `None` unless the year comes after both.
+        fn of_format(fmt: &str) -> Option<Self> {
+            let day = fmt.find("%d").or_else(|| fmt.find("%e"))?;
+            let month = fmt.find("%m")?;
+            let year = fmt.find("%Y").or_else(|| fmt.find("%y"))?;
+            match (year > day.max(month), day < month) {
+                (false, _) => None,
+                (true, true) => Some(DateOrder::DayFirst),
+                (true, false) => Some(DateOrder::MonthFirst),
+            }
+        }
+    }
+
+    /// Resolves the order of the day and month of dates such as `01/02/2021` (or `01.02.2021`) across an input.
+    ///
+    /// Unless an order is provided it is locked in by the first date with a day over 12.
+    /// Dates read before then that could be either order are an error of kind [`Ambiguous`](crate::error::ErrorKind::Ambiguous), they can be read again once the order is found.
+    /// Dates with the same day and month read the same either way.
+    ///
+    /// Once the order is known, dictionary entries written for the other order are skipped and formats for the order are tried when no entry matches.
+    #[derive(Debug, Clone, Default)]
+    pub struct DateOrderResolver {
+        order: Option<DateOrder>,
+    }
+
+    impl DateOrderResolver {
+        pub fn new(order: Option<DateOrder>) -> Self {
+            Self { order }
+        }
+        /// The order to read a timestamp in, if it starts with a day and month date.
+        pub fn resolve(&mut self, s: &str) -> Option<Result<DateOrder>> {
+            let captures = DAY_MONTH_REGEX.captures(s.trim())?;
+            let first: u32 = captures[1].parse().ok()?;
+            let second: u32 = captures[3].parse().ok()?;
+            if self.order.is_none() {
+                self.order = match (first, second) {
+                    (f, s) if f > 12 && s <= 12 => Some(DateOrder::DayFirst),
+                    (f, s) if s > 12 && f <= 12 => Some(DateOrder::MonthFirst),
+                    _ => None,
+                };
+                if let Some(order) = self.order {
+                    debug!("Found the date order {:?} from {}", order, s);
                 }
             }
-            Err(error::Error {
-                reason: format!("Unable to find format to parse {}", s),
-                kind: error::ErrorKind::DateTime,
-            })
+            match self.order {
+                Some(order) => Some(Ok(order)),
+                None if first == second => Some(Ok(DateOrder::DayFirst)),
+                None => Some(Err(error::Error {
+                    reason: format!(
+                        "{} could be day or month first, provide the order with dmy or mdy",
+                        s.trim()
+                    ),
+                    kind: error::ErrorKind::Ambiguous,
+                })),
+            }
+        }
+        /// Read a day and month date in the order provided with each of the [`DAY_MONTH_TIMES`], dates alone are read as midnight.
+        fn parse(
+            s: &str,
+            order: DateOrder,
+            tz: Option<&String>,
+            ambiguous: AmbiguousTime,
+        ) -> Option<DateTime<FixedOffset>> {
+            let s = s.trim();
+            let separator = &DAY_MONTH_REGEX.captures(s)?[2];
+            let date = match order {
+                DateOrder::DayFirst => format!("%d{0}%m{0}%Y", separator),
+                DateOrder::MonthFirst => format!("%m{0}%d{0}%Y", separator),
+            };
+            DAY_MONTH_TIMES
+                .iter()
+                .map(|t| format!("{} {}", date, t))
+                .find_map(|f| {
+                    parse_dt(s, Some(&format!("{} %z", f)))
+                        .or_else(|| parse_naive_dt_as(s, Some(&f), tz, ambiguous))
+                })
+                .or_else(|| {
+                    let midnight = NaiveDate::parse_from_str(s, &date)
+                        .ok()?
+                        .and_hms_opt(0, 0, 0)?;
+                    local_timezone(tz).from_local_as(&midnight, ambiguous).ok()
+                })
         }
     }
 
@@ -157,15 +908,22 @@ pub mod parsing {
             regex::Regex::new(r"(\+|\-)([0-1][0-9])([0-9]{2})").unwrap();
     }
     //
-    use chrono::{DateTime, FixedOffset};
+    use chrono::{
+        format::{Item, StrftimeItems},
+        DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveTime, Offset,
+        TimeZone, Utc,
+    };
+    use chrono_tz::Tz;
+    /// The UTC offset used whenever no timezone could be parsed.
+    pub fn utc_offset() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
     /// Parse a timestamp field and return a FixedOffset
     pub fn parse_fixed_offset(tz: Option<&String>) -> Result<FixedOffset> {
-        let timezone_regex_1: regex::Regex = regex::Regex::new(r"(\+|\-)([0-1][0-9]):([0-9]{2})")?;
-        let timezone_regex_2: regex::Regex = regex::Regex::new(r"(\+|\-)([0-1][0-9])([0-9]{2})")?;
         if let Some(tz_str) = tz.as_ref() {
             match (
-                timezone_regex_1.captures(tz_str),
-                timezone_regex_2.captures(tz_str),
+                TIME_ZONE_REGEX_1.captures(tz_str),
+                TIME_ZONE_REGEX_2.captures(tz_str),
             ) {
                 (Some(captures), None) | (None, Some(captures)) => {
                     let is_east: Option<bool> = captures.get(1).map(|b| b.as_str() == "+");
@@ -179,11 +937,13 @@ pub mod parsing {
                     };
                     if let (Some(e), Some(h), Some(m)) = (is_east, hours, minutes) {
                         let timezone = match e {
-                            true => FixedOffset::east((3600 * h + 60 * m).into()),
-                            false => FixedOffset::west((3600 * h + 60 * m).into()),
+                            true => FixedOffset::east_opt((3600 * h + 60 * m).into()),
+                            false => FixedOffset::west_opt((3600 * h + 60 * m).into()),
                         };
-                        debug!("Parsed timezone {} from {}", timezone, tz_str);
-                        return Ok(timezone);
+                        if let Some(timezone) = timezone {
+                            debug!("Parsed timezone {} from {}", timezone, tz_str);
+                            return Ok(timezone);
+                        }
                     }
                 }
                 _ => (),
@@ -200,19 +960,302 @@ pub mod parsing {
         };
         Err(err)
     }
-    /// Parse Integer Timestamps, not currently in use.
-    #[allow(dead_code)]
-    pub fn parse_integer(i: i64, n: u32, tz: Option<&String>) -> Option<DateTime<FixedOffset>> {
-        let timezone = match parse_fixed_offset(tz) {
-            Ok(t) => t,
-            Err(_) => FixedOffset::east(0),
+    /// The epochs (and units) that numeric timestamps are counted from.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum EpochKind {
+        /// Seconds since 1970-01-01.
+        Unix,
+        /// Milliseconds since 1970-01-01.
+        UnixMillis,
+        /// Microseconds since 1970-01-01.
+        UnixMicros,
+        /// Nanoseconds since 1970-01-01.
+        UnixNanos,
+        /// Windows FILETIME, 100 nanosecond intervals since 1601-01-01.
+        FileTime,
+        /// Chrome/WebKit time, microseconds since 1601-01-01.
+        WebKit,
+        /// Cocoa/Mac absolute time, seconds since 2001-01-01.
+        Cocoa,
+        /// HFS+ time, seconds since 1904-01-01.
+        HfsPlus,
+        /// Excel serial dates, days since 1899-12-30.
+        Excel,
+    }
+
+    impl EpochKind {
+        /// The names accepted by [`EpochKind::from_name`].
+        pub const NAMES: &'static [&'static str] = &[
+            "unix", "unix-ms", "unix-us", "unix-ns", "filetime", "webkit", "cocoa", "hfs", "excel",
+        ];
+        pub fn from_name(name: &str) -> Option<Self> {
+            match name.to_lowercase().as_str() {
+                "unix" | "epoch" => Some(EpochKind::Unix),
+                "unix-ms" | "ms" => Some(EpochKind::UnixMillis),
+                "unix-us" | "us" => Some(EpochKind::UnixMicros),
+                "unix-ns" | "ns" => Some(EpochKind::UnixNanos),
+                "filetime" | "windows" => Some(EpochKind::FileTime),
+                "webkit" | "chrome" => Some(EpochKind::WebKit),
+                "cocoa" | "mac" => Some(EpochKind::Cocoa),
+                "hfs" | "hfs+" => Some(EpochKind::HfsPlus),
+                "excel" => Some(EpochKind::Excel),
+                _ => None,
+            }
+        }
+        /// The name of the epoch, as accepted by [`EpochKind::from_name`].
+        pub fn name(&self) -> &'static str {
+            match self {
+                EpochKind::Unix => "unix",
+                EpochKind::UnixMillis => "unix-ms",
+                EpochKind::UnixMicros => "unix-us",
+                EpochKind::UnixNanos => "unix-ns",
+                EpochKind::FileTime => "filetime",
+                EpochKind::WebKit => "webkit",
+                EpochKind::Cocoa => "cocoa",
+                EpochKind::HfsPlus => "hfs",
+                EpochKind::Excel => "excel",
+            }
+        }
+        /// Guess the epoch from the magnitude of a timestamp, seconds are assumed for anything that could be a Unix timestamp.
+        /// Cocoa, HFS+ and Excel timestamps cannot be told apart from Unix seconds so they are never detected.
+        pub fn detect(value: f64) -> Self {
+            match value.abs() {
+                v if v < 1e11 => EpochKind::Unix,
+                v if v < 1e14 => EpochKind::UnixMillis,
+                v if v < 1e16 => EpochKind::UnixMicros,
+                v if v < 1e17 => EpochKind::WebKit,
+                v if v < 1e18 => EpochKind::FileTime,
+                _ => EpochKind::UnixNanos,
+            }
+        }
+        /// The nanoseconds in one unit and the seconds from 1970-01-01 to the epoch.
+        fn scale(&self) -> (i128, i128) {
+            match self {
+                EpochKind::Unix => (1_000_000_000, 0),
+                EpochKind::UnixMillis => (1_000_000, 0),
+                EpochKind::UnixMicros => (1_000, 0),
+                EpochKind::UnixNanos => (1, 0),
+                EpochKind::FileTime => (100, -11_644_473_600),
+                EpochKind::WebKit => (1_000, -11_644_473_600),
+                EpochKind::Cocoa => (1_000_000_000, 978_307_200),
+                EpochKind::HfsPlus => (1_000_000_000, -2_082_844_800),
+                EpochKind::Excel => (86_400_000_000_000, -2_209_161_600),
+            }
+        }
+    }
+
+    /// Parse a numeric timestamp counted from an epoch, the epoch is detected from the magnitude when no kind is provided.
+    pub fn parse_epoch(
+        s: &str,
+        kind: Option<EpochKind>,
+        tz: Option<&String>,
+    ) -> Option<DateTime<FixedOffset>> {
+        let s = s.trim();
+        if s.is_empty()
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.' || c == '-')
+        {
+            return None;
+        }
+        // Integers are kept exact, FILETIME values are too large for a float
+        let (kind, nanos) = match s.parse::<i128>() {
+            Ok(i) => {
+                let kind = kind.unwrap_or_else(|| EpochKind::detect(i as f64));
+                (kind, i.checked_mul(kind.scale().0)?)
+            }
+            Err(_) => {
+                let f: f64 = s.parse().ok()?;
+                let kind = kind.unwrap_or_else(|| EpochKind::detect(f));
+                // Floats are only precise to around a microsecond
+                let micros = (f * kind.scale().0 as f64 / 1_000.0).round() as i128;
+                (kind, micros * 1_000)
+            }
         };
-        match NaiveDateTime::from_timestamp_opt(i, n) {
-            Some(dt) => Some(DateTime::from_utc(dt, timezone)),
-            None => None,
+        let nanos = nanos.checked_add(kind.scale().1 * 1_000_000_000)?;
+        debug!("Parsed {} as {:?} epoch", s, kind);
+        parse_integer(
+            i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?,
+            nanos.rem_euclid(1_000_000_000) as u32,
+            tz,
+        )
+    }
+    /// How a local time that happens twice (when clocks go back) is read.
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub enum AmbiguousTime {
+        /// The first of the two times, before clocks go back.
+        #[default]
+        Earliest,
+        /// The second of the two times, after clocks go back.
+        Latest,
+        /// Neither, the timestamp is an error.
+        Error,
+    }
+
+    impl AmbiguousTime {
+        /// Read `earliest`, `latest` or `error`.
+        pub fn new(s: &str) -> Result<Self> {
+            match s.to_lowercase().as_str() {
+                "earliest" => Ok(AmbiguousTime::Earliest),
+                "latest" => Ok(AmbiguousTime::Latest),
+                "error" => Ok(AmbiguousTime::Error),
+                _ => Err(error::Error {
+                    reason: format!(
+                        "Unknown choice of ambiguous time \"{}\", choices available: earliest, latest, error",
+                        s
+                    ),
+                    kind: error::ErrorKind::Timezone,
+                }),
+            }
+        }
+    }
+
+    /// A timezone provided as an offset (`+01:00` or `+0100`) or an IANA name (`Europe/London`).
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Timezone {
+        Fixed(FixedOffset),
+        Named(Tz),
+    }
+
+    impl Default for Timezone {
+        fn default() -> Self {
+            Timezone::Fixed(utc_offset())
+        }
+    }
+
+    impl Timezone {
+        pub fn new(tz: &str) -> Result<Self> {
+            let tz_string = tz.to_string();
+            if let Ok(offset) = parse_fixed_offset(Some(&tz_string)) {
+                return Ok(Timezone::Fixed(offset));
+            }
+            match tz {
+                "Z" | "z" => Ok(Timezone::Fixed(utc_offset())),
+                _ => match tz.parse::<Tz>() {
+                    Ok(t) => Ok(Timezone::Named(t)),
+                    Err(_) => Err(crate::error::Error {
+                        reason: format!(
+                            "Could not convert \"{}\" into a timezone, use an offset (+01:00) or an IANA name (Europe/London)",
+                            tz
+                        ),
+                        kind: crate::error::ErrorKind::Timezone,
+                    }),
+                },
+            }
+        }
+        /// Interpret a local time in this timezone.
+        /// Local times that happen twice (when clocks go back) are read as the earliest, local times that are skipped (when clocks go forward) are an error.
+        pub fn from_local(&self, naive: &NaiveDateTime) -> Result<DateTime<FixedOffset>> {
+            self.from_local_as(naive, AmbiguousTime::default())
+        }
+        /// Interpret a local time in this timezone, choosing between the two times a repeated local time could be.
+        pub fn from_local_as(
+            &self,
+            naive: &NaiveDateTime,
+            ambiguous: AmbiguousTime,
+        ) -> Result<DateTime<FixedOffset>> {
+            let local = match self {
+                Timezone::Fixed(offset) => offset
+                    .from_local_datetime(naive)
+                    .map(|dt| dt.fixed_offset()),
+                Timezone::Named(tz) => tz.from_local_datetime(naive).map(|dt| dt.fixed_offset()),
+            };
+            match (local, ambiguous) {
+                (LocalResult::Single(dt), _) => Ok(dt),
+                (LocalResult::Ambiguous(earliest, latest), AmbiguousTime::Earliest) => {
+                    debug!(
+                        "{} is ambiguous in {:?}, using {} rather than {}",
+                        naive, self, earliest, latest
+                    );
+                    Ok(earliest)
+                }
+                (LocalResult::Ambiguous(earliest, latest), AmbiguousTime::Latest) => {
+                    debug!(
+                        "{} is ambiguous in {:?}, using {} rather than {}",
+                        naive, self, latest, earliest
+                    );
+                    Ok(latest)
+                }
+                (LocalResult::Ambiguous(earliest, latest), AmbiguousTime::Error) => {
+                    Err(crate::error::Error {
+                        reason: format!(
+                            "{} is ambiguous in {:?}, it could be {} or {}",
+                            naive, self, earliest, latest
+                        ),
+                        kind: crate::error::ErrorKind::Timezone,
+                    })
+                }
+                (LocalResult::None, _) => Err(crate::error::Error {
+                    reason: format!(
+                        "{} does not exist in {:?}, it is skipped when clocks go forward",
+                        naive, self
+                    ),
+                    kind: crate::error::ErrorKind::Timezone,
+                }),
+            }
+        }
+        /// Show an instant in this timezone.
+        pub fn from_utc(&self, dt: &DateTime<Utc>) -> DateTime<FixedOffset> {
+            match self {
+                Timezone::Fixed(offset) => dt.with_timezone(offset),
+                Timezone::Named(tz) => dt.with_timezone(tz).fixed_offset(),
+            }
+        }
+        /// Interpret a local time that is a boundary (such as the start of a bucket), this never fails.
+        /// A repeated local time uses the offset provided if it is one of the two, a skipped local time moves forward to the end of the gap.
+        pub fn boundary(
+            &self,
+            naive: &NaiveDateTime,
+            offset: FixedOffset,
+        ) -> DateTime<FixedOffset> {
+            let tz = match self {
+                Timezone::Fixed(offset) => {
+                    return DateTime::from_naive_utc_and_offset(*naive - *offset, *offset)
+                }
+                Timezone::Named(tz) => tz,
+            };
+            match tz.from_local_datetime(naive) {
+                LocalResult::Single(dt) => dt.fixed_offset(),
+                LocalResult::Ambiguous(earliest, latest) => match latest.offset().fix() == offset {
+                    true => latest.fixed_offset(),
+                    false => earliest.fixed_offset(),
+                },
+                LocalResult::None => {
+                    // The end of the gap is the instant the offset changes, between the local time read with the offsets after and before it
+                    let offset = |dt: NaiveDateTime| tz.offset_from_utc_datetime(&dt).fix();
+                    let before = offset(*naive - chrono::Duration::days(1));
+                    let after = offset(*naive + chrono::Duration::days(1));
+                    let (mut start, mut end) = (*naive - after, *naive - before);
+                    while end - start > chrono::Duration::nanoseconds(1) {
+                        let middle = start + (end - start) / 2;
+                        match offset(middle) == before {
+                            true => start = middle,
+                            false => end = middle,
+                        }
+                    }
+                    tz.from_utc_datetime(&end).fixed_offset()
+                }
+            }
         }
     }
 
+    /// Parse the timezone provided, UTC is used when there is none (or it is not a timezone).
+    pub fn local_timezone(tz: Option<&String>) -> Timezone {
+        match tz.map(|t| Timezone::new(t)) {
+            Some(Ok(t)) => t,
+            Some(Err(e)) => {
+                debug!("{}, using UTC", e.reason);
+                Timezone::default()
+            }
+            None => Timezone::default(),
+        }
+    }
+
+    /// Parse Integer Timestamps, seconds and nanoseconds since the Unix epoch.
+    pub fn parse_integer(i: i64, n: u32, tz: Option<&String>) -> Option<DateTime<FixedOffset>> {
+        DateTime::from_timestamp(i, n).map(|dt| local_timezone(tz).from_utc(&dt))
+    }
+
     /// Parse String Timestamp. Returns a NaiveDateTime
     pub fn parse_dt(s: &str, f: Option<&String>) -> Option<DateTime<FixedOffset>> {
         match f {
@@ -254,37 +1297,51 @@ pub mod parsing {
         f: Option<&String>,
         tz: Option<&String>,
     ) -> Option<DateTime<FixedOffset>> {
-        let timezone = match parse_fixed_offset(tz) {
-            Ok(t) => t,
-            Err(_) => FixedOffset::east(0),
+        parse_naive_dt_as(s, f, tz, AmbiguousTime::default())
+    }
+
+    /// Parse String Timestamp without a zone, choosing between the two times a repeated local time could be.
+    pub fn parse_naive_dt_as(
+        s: &str,
+        f: Option<&String>,
+        tz: Option<&String>,
+        ambiguous: AmbiguousTime,
+    ) -> Option<DateTime<FixedOffset>> {
+        let timezone = local_timezone(tz);
+        let from_local = |d: NaiveDateTime| match timezone.from_local_as(&d, ambiguous) {
+            Ok(dt) => Some(dt),
+            Err(e) => {
+                debug!("{}", e.reason);
+                None
+            }
         };
         match f {
             Some(fmt) => match NaiveDateTime::parse_from_str(s, fmt) {
                 Ok(d) => {
                     debug!("Parsed Date (Naive) with format {:?}: {}", f, d);
-                    Some(DateTime::from_utc(d, timezone))
+                    from_local(d)
                 }
                 Err(_) => None,
             },
             None => {
-                if let Ok(d) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+                if let Ok(d) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f") {
                     debug!(
                         "Parsed Date (Naive) with format {:?}: {}",
-                        "%Y-%m-%d %H:%M:%S", d
+                        "%Y-%m-%d %H:%M:%S%.f", d
                     );
-                    Some(DateTime::from_utc(d, timezone))
+                    from_local(d)
                 } else if let Ok(d) = NaiveDateTime::parse_from_str(s, "%a, %d %b %Y %H:%M:%S") {
                     debug!(
                         "Parsed Date (Naive) with format {:?}: {}",
                         "%a, %d %b %Y %H:%M:%S", d
                     );
-                    Some(DateTime::from_utc(d, timezone))
-                } else if let Ok(d) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") {
+                    from_local(d)
+                } else if let Ok(d) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
                     debug!(
                         "Parsed Date (Naive) with format {:?}: {}",
-                        "%Y-%m-%dT%H:%M:%S", d
+                        "%Y-%m-%dT%H:%M:%S%.f", d
                     );
-                    Some(DateTime::from_utc(d, timezone))
+                    from_local(d)
                 } else {
                     None
                 }
@@ -301,6 +1358,275 @@ mod tests {
     fn dict_from_file() {
         let i = parsing::FormatDictionary::from_file(PathBuf::from("./assets/default_formats.yml"));
         println!("{:?}", i);
-        assert!(false);
+        assert!(i.is_ok());
+    }
+    #[test]
+    fn complete_partial_timestamps() {
+        let anchor = DateTime::parse_from_rfc3339("2021-01-10T00:00:00+00:00").unwrap();
+        let mut dict = parsing::FormatDictionary::default().complete(Some(anchor));
+        let mut parse = |s: &str| {
+            let d = Data::from_dict(s, Vec::new(), None, None, &mut dict).unwrap();
+            (d.timestamp.to_rfc3339(), d.inferred)
+        };
+        // The anchor is in January so December is the year before
+        assert_eq!(
+            parse("12-31 23:59:30"),
+            ("2020-12-31T23:59:30+00:00".to_string(), true)
+        );
+        assert_eq!(
+            parse("00:00:05.5"),
+            ("2021-01-01T00:00:05.500+00:00".to_string(), true)
+        );
+        assert_eq!(
+            parse("2021-03-14 10:00:00"),
+            ("2021-03-14T10:00:00+00:00".to_string(), false)
+        );
+        assert_eq!(
+            parse("Mar 14"),
+            ("2021-03-14T00:00:00+00:00".to_string(), true)
+        );
+        dict.set_origin(Some("/var/log/app-2021-06-01.log"));
+        assert_eq!(
+            Data::from_dict("10:22:01", Vec::new(), None, None, &mut dict)
+                .unwrap()
+                .timestamp
+                .to_rfc3339(),
+            "2021-06-01T10:22:01+00:00"
+        );
+    }
+    #[test]
+    fn resolve_date_order() {
+        let mut dict = parsing::FormatDictionary::default().resolve_date_order(None);
+        let mut parse = |s: &str| {
+            Data::from_dict(s, Vec::new(), None, None, &mut dict).map(|d| d.timestamp.to_rfc3339())
+        };
+        assert!(matches!(
+            parse("01/02/2021 10:00:00").unwrap_err().kind,
+            error::ErrorKind::Ambiguous
+        ));
+        assert_eq!(parse("05/05/2021").unwrap(), "2021-05-05T00:00:00+00:00");
+        assert_eq!(
+            parse("13/02/2021 10:00:00.5").unwrap(),
+            "2021-02-13T10:00:00.500+00:00"
+        );
+        assert_eq!(
+            parse("01/02/2021 10:00:00").unwrap(),
+            "2021-02-01T10:00:00+00:00"
+        );
+        assert!(parse("02/13/2021 10:00:00").is_err());
+        let mut forced = parsing::FormatDictionary::default()
+            .resolve_date_order(Some(parsing::DateOrder::MonthFirst));
+        assert_eq!(
+            forced
+                .parse_datetime("01/02/2021 10:00:00", None)
+                .unwrap()
+                .to_rfc3339(),
+            "2021-01-02T10:00:00+00:00"
+        );
+    }
+    #[test]
+    fn resolve_date_order_with_dictionary() {
+        let entries = |order| {
+            let mut dict = parsing::FormatDictionary::default();
+            dict.insert("dmy", "%d/%m/%Y %H:%M");
+            dict.insert("mdy", "%m/%d/%Y %I:%M %p");
+            dict.resolve_date_order(order)
+        };
+        let mut dict = entries(None);
+        let mut parse = |s: &str| dict.parse_datetime(s, None).map(|d| d.to_rfc3339());
+        assert!(parse("01/02/2021 10:00").is_err());
+        assert_eq!(
+            parse("13/02/2021 10:00").unwrap(),
+            "2021-02-13T10:00:00+00:00"
+        );
+        assert_eq!(
+            parse("01/02/2021 10:00").unwrap(),
+            "2021-02-01T10:00:00+00:00"
+        );
+        // The month first entry is skipped, the time is read by the formats for the order found
+        assert_eq!(
+            parse("01/02/2021 10:00 PM").unwrap(),
+            "2021-02-01T22:00:00+00:00"
+        );
+        assert_eq!(
+            parse("01/02/2021 10:00 +0100").unwrap(),
+            "2021-02-01T10:00:00+01:00"
+        );
+        assert_eq!(dict.hits(), vec![("dmy", 2)]);
+        let mut forced = entries(Some(parsing::DateOrder::MonthFirst));
+        assert_eq!(
+            forced
+                .parse_datetime("01/02/2021 10:00 PM", None)
+                .unwrap()
+                .to_rfc3339(),
+            "2021-01-02T22:00:00+00:00"
+        );
+        assert_eq!(forced.hits(), vec![("mdy", 1)]);
+    }
+    #[test]
+    fn priority_counts_hits_and_persists() {
+        let mut dict = parsing::FormatDictionary::default();
+        dict.insert("date", "%Y-%m-%d %H:%M:%S");
+        dict.insert("slash", "%Y/%m/%d %H:%M:%S");
+        for s in [
+            "2021-03-01 10:00:00",
+            "2021/03/01 10:00:00",
+            "2021/03/01 11:00:00",
+        ] {
+            dict.parse_datetime(s, None).unwrap();
+        }
+        assert_eq!(dict.hits(), vec![("slash", 2), ("date", 1)]);
+        let file = std::env::temp_dir().join(format!(
+            "dta_priority_counts_hits_and_persists_{}.yml",
+            std::process::id()
+        ));
+        dict.save_priority(&file).unwrap();
+        let mut other = parsing::FormatDictionary::default();
+        other.insert("slash", "%Y/%m/%d %H:%M:%S");
+        let other = other.load_priority(&file).unwrap();
+        assert_eq!(other.hits(), vec![("slash", 2)]);
+        fs::remove_file(file).unwrap();
+    }
+    #[test]
+    fn preferred_format_is_tried_first() {
+        let mut dict = parsing::FormatDictionary::default();
+        dict.insert("dmy", "%d/%m/%Y %H:%M");
+        let mut dict = dict.prefer("date-format", "%m/%d/%Y %H:%M");
+        for _ in 0..2 {
+            let dt = dict.parse_datetime("01/02/2021 10:00", None).unwrap();
+            assert_eq!(dt.to_rfc3339(), "2021-01-02T10:00:00+00:00");
+        }
+        assert_eq!(dict.hits(), vec![("date-format", 2)]);
+    }
+    #[test]
+    fn merge_directory_over_bundled() {
+        let dir = std::env::temp_dir().join(format!(
+            "dta_merge_directory_over_bundled_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a.yml"),
+            "DEFAULT_001:\n  fmt: \"%d %B %Y %H:%M\"\n  locale: de\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b.yaml"),
+            "millis:\n  epoch: unix-ms\nparis:\n  fmt: \"%d/%m/%Y %H:%M\"\n  tz: Europe/Paris\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a dictionary").unwrap();
+        let mut dict = parsing::FormatDictionary::bundled()
+            .unwrap()
+            .merge(parsing::FormatDictionary::from_file(dir.clone()).unwrap());
+        let mut parse = |s: &str| dict.parse_datetime(s, None).unwrap().to_rfc3339();
+        assert_eq!(parse("14 März 2021 10:00"), "2021-03-14T10:00:00+00:00");
+        assert_eq!(parse("1614592800000"), "2021-03-01T10:00:00+00:00");
+        assert_eq!(parse("14/03/2021 10:00"), "2021-03-14T10:00:00+01:00");
+        assert_eq!(parse("2021-03-14 10:00:00"), "2021-03-14T10:00:00+00:00");
+        fs::write(dir.join("c.yml"), "bad:\n  epoch: lunar\n").unwrap();
+        assert!(parsing::FormatDictionary::from_file(dir.clone()).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn bundled_formats_are_valid_and_parse() {
+        let bundled = parsing::FormatDictionary::bundled().unwrap();
+        let dt = DateTime::parse_from_rfc3339("2021-03-01T10:00:00.250+01:00").unwrap();
+        for (name, fmt) in bundled.formats() {
+            // Each entry reads back the timestamp it writes
+            let s = dt.format(fmt).to_string();
+            let mut dict = parsing::FormatDictionary::default();
+            dict.insert(name, fmt);
+            let parsed = dict.parse_datetime(&s, None).unwrap();
+            assert_eq!(parsed.format(fmt).to_string(), s, "{} {}", name, fmt);
+            assert_eq!(dict.hits(), vec![(name, 1)], "{} {}", name, fmt);
+        }
+    }
+    #[test]
+    fn parse_in_named_timezone() {
+        let tz = Some("Europe/London".to_string());
+        let parse =
+            |s: &str| parsing::parse_naive_dt(s, None, tz.as_ref()).map(|dt| dt.to_rfc3339());
+        assert_eq!(
+            parse("2021-07-01 10:00:00").unwrap(),
+            "2021-07-01T10:00:00+01:00"
+        );
+        // Clocks went back at 02:00 on 2021-10-31 and forward at 01:00 on 2021-03-28
+        assert_eq!(
+            parse("2021-10-31 01:30:00").unwrap(),
+            "2021-10-31T01:30:00+01:00"
+        );
+        assert!(parse("2021-03-28 01:30:00").is_none());
+        let london = parsing::Timezone::new("Europe/London").unwrap();
+        let repeated =
+            NaiveDateTime::parse_from_str("2021-10-31 01:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let read = |a: &str| {
+            london
+                .from_local_as(&repeated, parsing::AmbiguousTime::new(a).unwrap())
+                .map(|dt| dt.to_rfc3339())
+        };
+        assert_eq!(read("latest").unwrap(), "2021-10-31T01:30:00+00:00");
+        assert!(read("error").is_err());
+        // A boundary skipped when clocks go forward moves to the end of the gap
+        let skipped =
+            NaiveDateTime::parse_from_str("2021-03-28 01:10:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(
+            london
+                .boundary(&skipped, parsing::utc_offset())
+                .to_rfc3339(),
+            "2021-03-28T02:00:00+01:00"
+        );
+        // Each dictionary keeps its own choice
+        let mut latest =
+            parsing::FormatDictionary::default().read_ambiguous(parsing::AmbiguousTime::Latest);
+        let mut earliest = parsing::FormatDictionary::default();
+        assert_eq!(
+            latest
+                .parse_datetime("2021-10-31 01:30:00", tz.as_ref())
+                .unwrap()
+                .to_rfc3339(),
+            "2021-10-31T01:30:00+00:00"
+        );
+        assert_eq!(
+            earliest
+                .parse_datetime("2021-10-31 01:30:00", tz.as_ref())
+                .unwrap()
+                .to_rfc3339(),
+            "2021-10-31T01:30:00+01:00"
+        );
+        assert_eq!(
+            parsing::parse_integer(1625130000, 0, tz.as_ref())
+                .unwrap()
+                .to_rfc3339(),
+            "2021-07-01T10:00:00+01:00"
+        );
+        assert!(parsing::Timezone::new("Mars/Olympus").is_err());
+    }
+    #[test]
+    fn parse_epoch_kinds() {
+        let parse = |s: &str, kind: Option<&str>| {
+            parsing::parse_epoch(s, kind.and_then(parsing::EpochKind::from_name), None)
+                .unwrap()
+                .to_rfc3339()
+        };
+        let expected = "2021-03-01T10:00:00+00:00";
+        assert_eq!(parse("1614592800", None), expected);
+        assert_eq!(parse("1614592800000", None), expected);
+        assert_eq!(parse("1614592800000000", None), expected);
+        assert_eq!(parse("1614592800000000000", None), expected);
+        assert_eq!(parse("132590664000000000", None), expected);
+        assert_eq!(parse("13259066400000000", None), expected);
+        assert_eq!(parse("636285600", Some("cocoa")), expected);
+        assert_eq!(parse("3697437600", Some("hfs")), expected);
+        assert_eq!(parse("44256.5", Some("excel")), "2021-03-01T12:00:00+00:00");
+        assert!(parsing::parse_epoch("2021-03-01", None, None).is_none());
+        // The epoch is kept apart from the format and used with a dictionary
+        let cocoa = Some(parsing::EpochKind::Cocoa);
+        let mut dict = parsing::FormatDictionary::bundled().unwrap();
+        let data = Data::from_dict("636285600", Vec::new(), cocoa, None, &mut dict).unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), expected);
+        let format = "%d/%m/%Y %H:%M".to_string();
+        let data = Data::new("01/03/2021 10:00", Some(&format), cocoa, None, Vec::new()).unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), expected);
     }
 }
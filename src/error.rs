@@ -16,13 +16,19 @@ pub struct Error {
 // Crate level error kind enum
 #[derive(Debug, Clone)]
 pub enum ErrorKind {
-    Data { raw: Vec<u8> },
+    Data {
+        raw: Vec<u8>,
+    },
     Parser,
     DateTime,
     Timezone,
     Aggregator,
     Input,
     Increment,
+    /// The entry was read by a parser but holds no data, for example a header row.
+    Skipped,
+    /// The timestamp could be read more than one way, such as a date that could be day or month first.
+    Ambiguous,
 }
 
 impl ErrTrait for Error {}
@@ -85,6 +91,15 @@ impl From<glob::GlobError> for Error {
     }
 }
 
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        Self {
+            reason: format!("{}", err),
+            kind: ErrorKind::Input,
+        }
+    }
+}
+
 impl From<chrono::RoundingError> for Error {
     fn from(err: chrono::RoundingError) -> Self {
         Self {
@@ -121,6 +136,15 @@ impl From<std::num::ParseIntError> for Error {
     }
 }
 
+impl From<std::num::ParseFloatError> for Error {
+    fn from(err: std::num::ParseFloatError) -> Self {
+        Self {
+            reason: format!("{}", err),
+            kind: ErrorKind::Increment,
+        }
+    }
+}
+
 impl From<chrono::format::ParseError> for Error {
     fn from(err: chrono::format::ParseError) -> Self {
         Self {
@@ -0,0 +1,224 @@
+//! # Access Log Parser
+//!
+//! The Access Log parser reads the timestamp from web server access logs, such as `127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 2326`.
+//!
+//! The Common and Combined Log Formats are built in, other layouts can be described with an nginx `log_format` string.
+//! The timestamp is read from the `$time_local`, `$time_iso8601` or `$msec` variable, every other variable matches any text.
+//!
+
+use crate::{
+    error::{Error, ErrorKind},
+    input::{Parser, Transform},
+    Data, Result,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+
+/// The Common Log Format as an nginx `log_format` string.
+pub const COMMON_LOG_FORMAT: &str =
+    "$remote_addr $remote_ident $remote_user [$time_local] \"$request\" $status $body_bytes_sent";
+/// The Combined Log Format as an nginx `log_format` string.
+pub const COMBINED_LOG_FORMAT: &str = "$remote_addr $remote_ident $remote_user [$time_local] \"$request\" $status $body_bytes_sent \"$http_referer\" \"$http_user_agent\"";
+
+const TIME_LOCAL_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+/// The variables a timestamp can be read from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TimeVariable {
+    Local,
+    Iso8601,
+    Msec,
+}
+
+impl TimeVariable {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "time_local" => Some(TimeVariable::Local),
+            "time_iso8601" => Some(TimeVariable::Iso8601),
+            "msec" => Some(TimeVariable::Msec),
+            _ => None,
+        }
+    }
+    fn pattern(&self) -> &'static str {
+        match self {
+            TimeVariable::Local => r"(?P<ts>\d{2}/[A-Za-z]{3}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4})",
+            TimeVariable::Iso8601 => {
+                r"(?P<ts>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:Z|[+-]\d{2}:\d{2}))"
+            }
+            TimeVariable::Msec => r"(?P<ts>\d+(?:\.\d+)?)",
+        }
+    }
+    fn parse(&self, s: &str) -> Option<DateTime<FixedOffset>> {
+        match self {
+            TimeVariable::Local => DateTime::parse_from_str(s, TIME_LOCAL_FORMAT).ok(),
+            TimeVariable::Iso8601 => DateTime::parse_from_rfc3339(s).ok(),
+            TimeVariable::Msec => {
+                let seconds: f64 = s.parse().ok()?;
+                DateTime::<Utc>::from_timestamp(
+                    seconds.trunc() as i64,
+                    (seconds.fract() * 1e9).round() as u32,
+                )
+                .map(|dt| dt.into())
+            }
+        }
+    }
+}
+
+pub struct AccessLogParser {
+    regex: regex::Regex,
+    time: TimeVariable,
+}
+
+impl AccessLogParser {
+    /// Create an `AccessLogParser` from an nginx `log_format` string, `common` and `combined` select the built in formats.
+    pub fn new(log_format: &str) -> Result<Self> {
+        let log_format = match log_format {
+            "common" => COMMON_LOG_FORMAT,
+            "combined" => COMBINED_LOG_FORMAT,
+            f => f,
+        };
+        let mut pattern = String::from("^");
+        let mut time = None;
+        let mut rest = log_format;
+        while let Some(start) = rest.find('$') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let variable = &rest[start + 1..];
+            // Variables are either $name or ${name}
+            let (name, len) = match variable.strip_prefix('{') {
+                Some(v) => match v.find('}') {
+                    Some(end) => (&v[..end], end + 2),
+                    None => (v, v.len() + 1),
+                },
+                None => {
+                    let end = variable
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(variable.len());
+                    (&variable[..end], end)
+                }
+            };
+            match (TimeVariable::from_name(name), time) {
+                (Some(t), None) => {
+                    pattern.push_str(t.pattern());
+                    time = Some(t);
+                }
+                _ => pattern.push_str(".*?"),
+            }
+            rest = &variable[len..];
+        }
+        pattern.push_str(&regex::escape(rest));
+        let time = match time {
+            Some(t) => t,
+            None => {
+                return Err(Error {
+                    reason: format!(
+                        "Log format \"{}\" has no $time_local, $time_iso8601 or $msec variable",
+                        log_format
+                    ),
+                    kind: ErrorKind::Parser,
+                })
+            }
+        };
+        debug!("Access log format {} read as {}", log_format, pattern);
+        Ok(Self {
+            regex: regex::Regex::new(&pattern)?,
+            time,
+        })
+    }
+}
+
+impl Parser for AccessLogParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        _fmt: Option<&String>,
+        _epoch: Option<crate::EpochKind>,
+        _tz: Option<&String>,
+        _dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        // Parse raw data back into a string
+        use std::str;
+        let raw_data = match str::from_utf8(&raw[..]) {
+            Ok(d) => d,
+            Err(e) => {
+                let err = Error {
+                    reason: format!("Data coverted: {}", e),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let ts = match self.regex.captures(raw_data).and_then(|c| c.name("ts")) {
+            Some(ts) => ts,
+            None => {
+                let err = Error {
+                    reason: format!("Line does not match the access log format: {}", raw_data),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let timestamp = match self.time.parse(ts.as_str()) {
+            Some(t) => t,
+            None => {
+                return Err(Error {
+                    reason: format!("{} cannot be parsed.", ts.as_str()),
+                    kind: ErrorKind::DateTime,
+                })
+            }
+        };
+        let mut data = Data {
+            timestamp,
+            raw: raw.clone(),
+            origin: None,
+            tag: None,
+            inferred: false,
+        };
+        if let Some(t) = transform {
+            let dt = t.apply(&data.timestamp);
+            let mut new = raw_data.to_string();
+            new.replace_range(ts.range(), &dt);
+            data.raw = new.as_bytes().to_vec();
+        }
+        debug!("Parsed data from raw bytes: {:?}", data);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn parse_combined() {
+        let mut parser = AccessLogParser::new("combined").unwrap();
+        let data = parser
+            .parse_data(
+                br#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /a.gif HTTP/1.0" 200 2326 "http://example.com/" "Mozilla/4.08""#.to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2000-10-10T13:55:36-07:00");
+    }
+    #[test]
+    fn parse_nginx_log_format() {
+        let mut parser = AccessLogParser::new("$msec ${remote_addr} \"$request\"").unwrap();
+        let data = parser
+            .parse_data(
+                br#"1614592800.250 10.0.0.1 "GET / HTTP/1.1""#.to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2021-03-01T10:00:00.250+00:00");
+        assert!(AccessLogParser::new("$remote_addr").is_err());
+    }
+}
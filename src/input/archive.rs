@@ -0,0 +1,216 @@
+//! # Archive Source
+//!
+//! The archive source component is used to read in the members of a tar or zip archive line by line.
+//!
+//! Tar archives can be compressed with any format supported by [`compression`](crate::input::compression), as can the individual members of either archive type.
+//! Members can be filtered with a glob matched against their path inside the archive, the path of the member currently being read is available through [`Source::origin`](crate::input::Source::origin).
+//! The modification time recorded for the member is available through [`Source::modified`](crate::input::Source::modified), zip times have no timezone and are read as UTC.
+//!
+
+use crate::{
+    input::{compression, Source},
+    Result,
+};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use std::{
+    fs,
+    io::prelude::*,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread,
+};
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZIP_EMPTY_MAGIC: &[u8] = b"PK\x05\x06";
+// Number of lines buffered between the archive walker and the reader
+const CHANNEL_BOUND: usize = 1024;
+
+type Line = Result<(String, Option<DateTime<FixedOffset>>, Vec<u8>)>;
+
+pub struct ArchiveSource {
+    receiver: Receiver<Line>,
+    current_member: Option<String>,
+    current_modified: Option<DateTime<FixedOffset>>,
+}
+
+impl ArchiveSource {
+    /// Function to create an `ArchiveSource`, provide:
+    /// * The path to a tar (optionally compressed) or zip archive.
+    /// * An optional glob, only members with a path matching the glob are read.
+    pub fn new(path: PathBuf, members: Option<&str>) -> Result<Self> {
+        let pattern = match members {
+            Some(m) => Some(glob::Pattern::new(m)?),
+            None => None,
+        };
+        let mut magic = [0; 4];
+        let len = fs::File::open(&path)?.read(&mut magic)?;
+        let is_zip =
+            magic[..len].starts_with(ZIP_MAGIC) || magic[..len].starts_with(ZIP_EMPTY_MAGIC);
+        // Archive entries borrow the archive, so members are walked on their own thread
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_BOUND);
+        thread::spawn(move || {
+            let walked = match is_zip {
+                true => walk_zip(&path, pattern.as_ref(), &sender),
+                false => walk_tar(&path, pattern.as_ref(), &sender),
+            };
+            if let Err(e) = walked {
+                let _ = sender.send(Err(e));
+            }
+        });
+        Ok(Self {
+            receiver,
+            current_member: None,
+            current_modified: None,
+        })
+    }
+}
+
+impl Source for ArchiveSource {
+    fn read_data(&mut self) -> Result<Vec<u8>> {
+        match self.receiver.recv() {
+            Ok(Ok((member, modified, line))) => {
+                debug!(
+                    "Reading {} bytes from {}: \"{}\"",
+                    line.len(),
+                    member,
+                    String::from_utf8_lossy(&line)
+                );
+                self.current_member = Some(member);
+                self.current_modified = modified;
+                Ok(line)
+            }
+            Ok(Err(e)) => Err(e),
+            // Walker has finished and dropped the sender
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+    fn origin(&self) -> Option<String> {
+        self.current_member.clone()
+    }
+    fn modified(&self) -> Option<DateTime<FixedOffset>> {
+        self.current_modified
+    }
+}
+
+fn is_selected(member: &str, pattern: Option<&glob::Pattern>) -> bool {
+    match pattern {
+        Some(p) => p.matches(member),
+        None => true,
+    }
+}
+
+/// Send each non-empty line of a member, returns false once the receiving side has gone away.
+fn send_lines(
+    member: &str,
+    modified: Option<DateTime<FixedOffset>>,
+    reader: impl Read,
+    sender: &SyncSender<Line>,
+) -> Result<bool> {
+    let mut reader = compression::decode(reader)?;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(true);
+        }
+        while let Some(b'\n') | Some(b'\r') = line.last() {
+            line.pop();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if sender
+            .send(Ok((member.to_string(), modified, line.clone())))
+            .is_err()
+        {
+            return Ok(false);
+        }
+    }
+}
+
+fn walk_tar(path: &Path, pattern: Option<&glob::Pattern>, sender: &SyncSender<Line>) -> Result<()> {
+    let file = fs::File::open(path)?;
+    let mut archive = tar::Archive::new(compression::decode(file)?);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let member = entry.path()?.display().to_string();
+        if !is_selected(&member, pattern) {
+            continue;
+        }
+        debug!("Reading tar member {}", member);
+        // A zero modification time is left out of many archives rather than recorded
+        let modified = match entry.header().mtime() {
+            Ok(0) | Err(_) => None,
+            Ok(m) => DateTime::from_timestamp(m as i64, 0).map(|dt| dt.into()),
+        };
+        if !send_lines(&member, modified, entry, sender)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn walk_zip(path: &Path, pattern: Option<&glob::Pattern>, sender: &SyncSender<Line>) -> Result<()> {
+    let file = fs::File::open(path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if !entry.is_file() {
+            continue;
+        }
+        let member = entry.name().to_string();
+        if !is_selected(&member, pattern) {
+            continue;
+        }
+        debug!("Reading zip member {}", member);
+        let m = entry.last_modified();
+        let modified = NaiveDate::from_ymd_opt(m.year().into(), m.month().into(), m.day().into())
+            .and_then(|d| d.and_hms_opt(m.hour().into(), m.minute().into(), m.second().into()))
+            .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc).into());
+        if !send_lines(&member, modified, entry, sender)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn read_tar_members() {
+        // Unique to the process so parallel test runs do not share the archive
+        let path =
+            std::env::temp_dir().join(format!("dta_archive_test_{}.tar", std::process::id()));
+        let mut builder = tar::Builder::new(fs::File::create(&path).unwrap());
+        for (name, body) in [
+            (
+                "logs/a.log",
+                &b"2020-01-01 12:00:00\n\n2020-01-01 13:00:00\n"[..],
+            ),
+            ("other/b.txt", &b"2020-01-01 14:00:00\n"[..]),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mtime(1609459200);
+            header.set_cksum();
+            builder.append_data(&mut header, name, body).unwrap();
+        }
+        builder.finish().unwrap();
+        drop(builder);
+        let mut source = ArchiveSource::new(path.clone(), Some("logs/*")).unwrap();
+        assert_eq!(source.read_data().unwrap(), b"2020-01-01 12:00:00");
+        assert_eq!(source.origin(), Some("logs/a.log".to_string()));
+        assert_eq!(
+            source.modified().map(|m| m.to_rfc3339()),
+            Some("2021-01-01T00:00:00+00:00".to_string())
+        );
+        assert_eq!(source.read_data().unwrap(), b"2020-01-01 13:00:00");
+        assert!(source.read_data().unwrap().is_empty());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+//! # Bodyfile Parser
+//!
+//! The Bodyfile parser reads Sleuth Kit bodyfiles (`MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime`), as written by `fls -m` and used by `mactime`.
+//!
+//! Each row holds up to four timestamps, the row is fanned out into one entry per distinct timestamp, tagged with its MACB role (such as `m.c.` when the modified and changed times are the same).
+//! Timestamps are seconds since the Unix epoch in UTC, a value of `0` or `-1` means the timestamp is not set.
+//!
+
+use crate::{
+    data::parsing,
+    error::{Error, ErrorKind},
+    input::{Parser, Transform},
+    Data, Result,
+};
+use chrono::{DateTime, FixedOffset};
+
+/// The number of fields after the file name, a name holding `|` is rejoined from the remaining fields.
+const FIELDS_AFTER_NAME: usize = 9;
+
+/// The MACB roles in the order they are shown, with their position in the fields after the name.
+const ROLES: [(char, usize); 4] = [('m', 6), ('a', 5), ('c', 7), ('b', 8)];
+
+#[derive(Default)]
+pub struct BodyfileParser;
+
+impl BodyfileParser {
+    /// Read a bodyfile timestamp, unset timestamps are `None`.
+    fn timestamp(field: &str, tz: Option<&String>) -> Result<Option<DateTime<FixedOffset>>> {
+        let seconds: f64 = match field.trim().parse() {
+            Ok(s) => s,
+            Err(_) => {
+                return Err(Error {
+                    reason: format!("{} is not a bodyfile timestamp", field),
+                    kind: ErrorKind::DateTime,
+                })
+            }
+        };
+        if seconds <= 0.0 {
+            return Ok(None);
+        }
+        let timestamp = parsing::parse_integer(
+            seconds.trunc() as i64,
+            (seconds.fract() * 1e9).round() as u32,
+            tz,
+        );
+        match timestamp {
+            Some(t) => Ok(Some(t)),
+            None => Err(Error {
+                reason: format!("{} is out of range", field),
+                kind: ErrorKind::DateTime,
+            }),
+        }
+    }
+}
+
+impl Parser for BodyfileParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        match self
+            .parse_entries(raw, fmt, epoch, tz, dict, transform)?
+            .into_iter()
+            .next()
+        {
+            Some(d) => Ok(d),
+            None => Err(Error {
+                reason: "Bodyfile row has no timestamps set".to_string(),
+                kind: ErrorKind::Skipped,
+            }),
+        }
+    }
+    /// Fan a row out into one entry per distinct timestamp, in timestamp order.
+    fn parse_entries(
+        &mut self,
+        raw: Vec<u8>,
+        _fmt: Option<&String>,
+        _epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        _dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Vec<Data>> {
+        // Parse raw data back into a string
+        use std::str;
+        let raw_data = match str::from_utf8(&raw[..]) {
+            Ok(d) => d,
+            Err(e) => {
+                let err = Error {
+                    reason: format!("Data coverted: {}", e),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let fields: Vec<&str> = raw_data.rsplitn(FIELDS_AFTER_NAME + 1, '|').collect();
+        // The name and MD5 are left in the last field
+        if fields.len() <= FIELDS_AFTER_NAME || !fields[FIELDS_AFTER_NAME].contains('|') {
+            let err = Error {
+                reason: format!("Not a bodyfile row: {}", raw_data),
+                kind: ErrorKind::Parser,
+            };
+            error!("Error occured during parsing: {:?}", err);
+            return Err(err);
+        }
+        let mut times: Vec<(DateTime<FixedOffset>, String)> = Vec::new();
+        for (role, position) in ROLES.iter() {
+            // rsplitn returns the fields from the right
+            let timestamp = match Self::timestamp(fields[FIELDS_AFTER_NAME - 1 - position], tz)? {
+                Some(t) => t,
+                None => continue,
+            };
+            match times.iter_mut().find(|(t, _)| *t == timestamp) {
+                Some((_, roles)) => roles.push(*role),
+                None => times.push((timestamp, role.to_string())),
+            }
+        }
+        times.sort_by_key(|(t, _)| *t);
+        let entries: Vec<Data> = times
+            .into_iter()
+            .map(|(timestamp, roles)| {
+                let macb: String = ROLES
+                    .iter()
+                    .map(|(r, _)| if roles.contains(*r) { *r } else { '.' })
+                    .collect();
+                let line = match transform {
+                    Some(t) => format!("{}|{}|{}", t.apply(&timestamp), macb, raw_data),
+                    None => format!("{}|{}", macb, raw_data),
+                };
+                Data {
+                    timestamp,
+                    raw: line.into_bytes(),
+                    origin: None,
+                    tag: Some(macb),
+                    inferred: false,
+                }
+            })
+            .collect();
+        debug!(
+            "Parsed {} entries from raw bytes: {:?}",
+            entries.len(),
+            entries
+        );
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn fan_out_macb() {
+        let mut parser = BodyfileParser;
+        let entries = parser
+            .parse_entries(
+                b"0|/tmp/a|b.txt|12|r/rrw-r--r--|0|0|5|1614592900|1614592800|1614592800|0".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].tag.as_deref(), Some("m.c."));
+        assert_eq!(
+            entries[0].timestamp.to_rfc3339(),
+            "2021-03-01T10:00:00+00:00"
+        );
+        assert_eq!(entries[1].tag.as_deref(), Some(".a.."));
+        assert_eq!(
+            entries[1].as_string().unwrap(),
+            ".a..|0|/tmp/a|b.txt|12|r/rrw-r--r--|0|0|5|1614592900|1614592800|1614592800|0"
+        );
+        assert!(parser
+            .parse_entries(b"not|a|bodyfile".to_vec(), None, None, None, None, None)
+            .is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! # Compression
+//!
+//! Transparent decompression for sources, the compression used is detected from the magic bytes at the start of a stream rather than a file extension.
+//!
+//! Supported formats include gzip, bzip2, xz and zstd, anything else is read as plain text.
+
+use crate::Result;
+use std::io::{prelude::*, BufReader};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression formats that can be detected and decoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Identify the compression format from the first bytes of a stream.
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if magic.starts_with(BZIP2_MAGIC) {
+            Compression::Bzip2
+        } else if magic.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else if magic.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wrap a reader in the decoder matching its magic bytes, the returned reader yields the decompressed data.
+pub fn decode<'a, R: Read + 'a>(reader: R) -> Result<Box<dyn BufRead + 'a>> {
+    Ok(decode_detected(reader)?.1)
+}
+
+/// Same as [`decode`], also returning the compression that was detected.
+pub fn decode_detected<'a, R: Read + 'a>(
+    reader: R,
+) -> Result<(Compression, Box<dyn BufRead + 'a>)> {
+    let mut reader = BufReader::new(reader);
+    let compression = Compression::detect(reader.fill_buf()?);
+    debug!("Detected compression {:?}", compression);
+    let decoded: Box<dyn BufRead + 'a> = match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        Compression::Xz => Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(
+            reader,
+        ))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(
+            reader,
+        )?)),
+    };
+    Ok((compression, decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn decode_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"2020-01-01 12:00:00\n").unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(Compression::detect(&compressed), Compression::Gzip);
+        let mut line = String::new();
+        decode(&compressed[..])
+            .unwrap()
+            .read_line(&mut line)
+            .unwrap();
+        assert_eq!(line, "2020-01-01 12:00:00\n");
+    }
+    #[test]
+    fn decode_plain() {
+        let mut line = String::new();
+        decode(&b"2020-01-01 12:00:00\n"[..])
+            .unwrap()
+            .read_line(&mut line)
+            .unwrap();
+        assert_eq!(line, "2020-01-01 12:00:00\n");
+    }
+}
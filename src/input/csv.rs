@@ -2,31 +2,166 @@
 //!
 //! The Csv parser is used to read in data from a Csv source and parse out a date time field to be used in aggregation.
 //!
+//! The timestamp column can be selected by position (starting at 0) or by name, selecting by name requires the first row to be a header.
+//! A timestamp split over several columns can be selected with a [`TimestampTemplate`], such as `{date} {time}` or `{0} {1}`.
+//! When selecting by position the first row is treated as a header if its timestamp field cannot be parsed, unless [`CsvParser::has_headers`] is used.
+//! The header row is not returned as data, it is available from [`Parser::header`](crate::input::Parser::header) so outputs can reproduce it.
+//!
 
 use crate::{
     error::{Error, ErrorKind},
-    input::Parser,
+    input::{template::TimestampTemplate, Parser, Transform},
     Data, Result,
 };
 
+/// The column (or columns) the timestamp is read from.
+#[derive(Debug, Clone)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+    Template(TimestampTemplate),
+}
+
+impl CsvColumn {
+    pub fn new(s: &str) -> Result<Self> {
+        if TimestampTemplate::is_template(s) {
+            return Ok(CsvColumn::Template(TimestampTemplate::new(s)?));
+        }
+        Ok(match s.parse() {
+            Ok(i) => CsvColumn::Index(i),
+            Err(_) => CsvColumn::Name(s.to_string()),
+        })
+    }
+    /// The column names that have to be found in a header row.
+    fn names(&self) -> Vec<&str> {
+        match self {
+            CsvColumn::Index(_) => Vec::new(),
+            CsvColumn::Name(n) => vec![n.as_str()],
+            CsvColumn::Template(t) => t.fields().filter(|f| f.parse::<usize>().is_err()).collect(),
+        }
+    }
+}
+
 pub struct CsvParser {
-    level: u8,
+    column: CsvColumn,
+    builder: csv::ReaderBuilder,
+    has_headers: Option<bool>,
+    header: Option<Vec<u8>>,
+    header_record: Option<csv::StringRecord>,
+    first_row: bool,
 }
 
 impl CsvParser {
-    pub fn new(level: u8) -> Self {
-        Self { level }
+    /// Create a `CsvParser`, the column is either a position (starting at 0), the name of a header field or a template of several fields.
+    pub fn new(column: &str) -> Result<Self> {
+        let mut builder = csv::ReaderBuilder::new();
+        builder.has_headers(false).flexible(true);
+        Ok(Self {
+            column: CsvColumn::new(column)?,
+            builder,
+            has_headers: None,
+            header: None,
+            header_record: None,
+            first_row: true,
+        })
+    }
+    /// Set the field delimiter, defaults to `,`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.builder.delimiter(delimiter);
+        self
+    }
+    /// Set the quote character, defaults to `"`.
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.builder.quote(quote);
+        self
+    }
+    /// Force whether the first row is a header rather than detecting it.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = Some(has_headers);
+        self
+    }
+    /// Read a field by position or by its name in the header row.
+    pub(crate) fn field<'r>(&self, record: &'r csv::StringRecord, field: &str) -> Option<&'r str> {
+        let index = match field.parse::<usize>() {
+            Ok(i) => i,
+            Err(_) => self
+                .header_record
+                .as_ref()?
+                .iter()
+                .position(|f| f.trim() == field)?,
+        };
+        record.get(index)
+    }
+    /// Read the timestamp string from a record.
+    fn timestamp(&self, record: &csv::StringRecord) -> Option<String> {
+        match &self.column {
+            CsvColumn::Index(i) => record.get(*i).map(str::to_string),
+            CsvColumn::Name(n) => self.field(record, n).map(str::to_string),
+            CsvColumn::Template(t) => t.render(|f| self.field(record, f).map(str::to_string)),
+        }
+    }
+    /// Read a single record from a line.
+    pub(crate) fn read_record(&self, raw: &str) -> Result<Option<csv::StringRecord>> {
+        match self.builder.from_reader(raw.as_bytes()).records().next() {
+            Some(res) => Ok(Some(res?)),
+            None => Ok(None),
+        }
+    }
+    /// Handle the first row, returns true if it was a header.
+    /// `parsed` is the first row read as data, it is detected as a header when its timestamp could not be read.
+    fn read_header(
+        &mut self,
+        raw: &[u8],
+        record: &csv::StringRecord,
+        parsed: Option<&Result<Data>>,
+    ) -> Result<bool> {
+        let names = self.column.names();
+        let is_header = match (names.first(), self.has_headers) {
+            (Some(name), Some(false)) => {
+                return Err(Error {
+                    reason: format!(
+                        "Column \"{}\" cannot be selected by name without a header row",
+                        name
+                    ),
+                    kind: ErrorKind::Parser,
+                })
+            }
+            (Some(_), _) => true,
+            (None, Some(h)) => h,
+            // Detect a header from the timestamp field not being a timestamp
+            (None, None) => match parsed {
+                Some(Err(e)) => !matches!(e.kind, ErrorKind::Skipped | ErrorKind::Ambiguous),
+                _ => false,
+            },
+        };
+        if !is_header {
+            return Ok(false);
+        }
+        if let Some(name) = names
+            .iter()
+            .find(|n| !record.iter().any(|f| f.trim() == **n))
+        {
+            return Err(Error {
+                reason: format!("Column \"{}\" is not in the CSV header", name),
+                kind: ErrorKind::Parser,
+            });
+        }
+        debug!("Read CSV header: {:?}", record);
+        self.header_record = Some(record.clone());
+        self.header = Some(raw.to_vec());
+        Ok(true)
     }
 }
 
 impl Parser for CsvParser {
     fn parse_data(
-        &self,
+        &mut self,
         raw: Vec<u8>,
         fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
         tz: Option<&String>,
         dict: Option<&mut crate::FormatDictionary>,
-        transform: Option<&String>,
+        transform: Option<&Transform>,
     ) -> Result<Data> {
         // Parse raw data back into a string
         use std::str;
@@ -41,19 +176,38 @@ impl Parser for CsvParser {
                 return Err(err);
             }
         };
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(raw_data.as_bytes());
-        if let Some(res) = reader.records().next() {
-            let v: csv::StringRecord = res?;
-            if let Some(ts_str) = v.get(self.level as usize) {
-                let mut data = match dict {
-                    Some(d) => Data::from_dict(&ts_str, raw.clone(), tz, d)?,
-                    None => Data::new(&ts_str, fmt, tz, raw.clone())?,
+        let read = |ts: &str, dict: Option<&mut crate::FormatDictionary>| match dict {
+            Some(d) => Data::from_dict(ts, raw.clone(), epoch, tz, d),
+            None => Data::new(ts, fmt, epoch, tz, raw.clone()),
+        };
+        let mut dict = dict;
+        if let Some(v) = self.read_record(raw_data)? {
+            let mut parsed = None;
+            if self.first_row {
+                self.first_row = false;
+                // The first row is read the same way as the rest to find if it is a header
+                parsed = self.timestamp(&v).map(|ts| read(&ts, dict.as_deref_mut()));
+                if self.read_header(&raw, &v, parsed.as_ref())? {
+                    return Err(Error {
+                        reason: format!("Skipped CSV header: {}", raw_data),
+                        kind: ErrorKind::Skipped,
+                    });
+                }
+            }
+            if let Some(ts_str) = self.timestamp(&v) {
+                let mut data = match parsed {
+                    Some(p) => p?,
+                    None => read(&ts_str, dict)?,
                 };
+                if let (Some(_), CsvColumn::Template(_)) = (transform, &self.column) {
+                    return Err(Error {
+                        reason: "A transform cannot be applied to a timestamp built from several columns".to_string(),
+                        kind: ErrorKind::Parser,
+                    });
+                }
                 if let Some(t) = transform {
-                    let dt = data.timestamp.format(t).to_string();
-                    let new = str::replace(raw_data, ts_str, &dt);
+                    let dt = t.apply(&data.timestamp);
+                    let new = str::replace(raw_data, &ts_str, &dt);
                     data.raw = new.as_bytes().to_vec();
                 }
                 debug!("Parsed data from raw bytes: {:?}", data);
@@ -67,4 +221,87 @@ impl Parser for CsvParser {
         error!("Error occured during parsing: {:?}", err);
         Err(err)
     }
+    fn header(&self) -> Option<Vec<u8>> {
+        self.header.clone()
+    }
+    /// Each file has its own header row.
+    fn set_origin(&mut self, _origin: Option<&str>) {
+        self.first_row = true;
+        self.header = None;
+        self.header_record = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn select_column_by_name() {
+        let mut parser = CsvParser::new("time").unwrap().delimiter(b'|');
+        let header = parser.parse_data(b"id|time".to_vec(), None, None, None, None, None);
+        assert!(matches!(header.unwrap_err().kind, ErrorKind::Skipped));
+        assert_eq!(parser.header(), Some(b"id|time".to_vec()));
+        let data = parser
+            .parse_data(
+                b"1|2020-01-01 12:00:00".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(data.timestamp.to_string(), "2020-01-01 12:00:00 +00:00");
+    }
+    #[test]
+    fn detect_header_by_index() {
+        let mut parser = CsvParser::new("1").unwrap();
+        assert!(parser
+            .parse_data(
+                b"1,2020-01-01 12:00:00".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None
+            )
+            .is_ok());
+        assert_eq!(parser.header(), None);
+        let mut parser = CsvParser::new("1").unwrap();
+        assert!(parser
+            .parse_data(b"id,timestamp".to_vec(), None, None, None, None, None)
+            .is_err());
+        assert_eq!(parser.header(), Some(b"id,timestamp".to_vec()));
+        // A first row read by the format dictionary is data, each file has its own header
+        let mut dict = crate::FormatDictionary::default();
+        dict.insert("dmy", "%d/%m/%Y %H:%M");
+        let mut parser = CsvParser::new("1").unwrap();
+        let mut parse = |parser: &mut CsvParser, raw: &[u8]| {
+            parser.parse_data(raw.to_vec(), None, None, None, Some(&mut dict), None)
+        };
+        assert!(parse(&mut parser, b"1,13/02/2021 10:00").is_ok());
+        assert_eq!(parser.header(), None);
+        parser.set_origin(Some("b.csv"));
+        assert!(parse(&mut parser, b"id,time").is_err());
+        assert_eq!(parser.header(), Some(b"id,time".to_vec()));
+        assert!(parse(&mut parser, b"2,14/02/2021 10:00").is_ok());
+    }
+    #[test]
+    fn assemble_timestamp_from_columns() {
+        let mut parser = CsvParser::new("{date} {1}").unwrap();
+        let header = parser.parse_data(b"date,time".to_vec(), None, None, None, None, None);
+        assert!(matches!(header.unwrap_err().kind, ErrorKind::Skipped));
+        let data = parser
+            .parse_data(
+                b"2020-01-01,12:00:00".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(data.timestamp.to_string(), "2020-01-01 12:00:00 +00:00");
+    }
 }
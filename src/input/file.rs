@@ -2,16 +2,46 @@
 //!
 //! The file source component is used to read in a file or multiple files based on a glob.
 //!
+//! Compressed files (gzip, bzip2, xz and zstd) are detected by their magic bytes and decompressed as they are read.
+//!
+//! With [`FileSource::follow`] the last file matched by the glob is followed as it grows (like `tail -F`).
+//! Truncated files are read again from the start and rotated files (a new file at the same path) are reopened.
+//! Whilst following, [`read_data`](Source::read_data) returns an empty entry when no new data arrived during the poll interval.
+//!
 
-use crate::{error, input::Source, Result};
+use crate::{
+    error,
+    input::{
+        compression::{self, Compression},
+        Source,
+    },
+    Result,
+};
+use chrono::{DateTime, FixedOffset, Utc};
 use std::{
     fs,
-    io::{prelude::*, BufReader},
+    io::prelude::*,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
 pub struct FileSource {
-    glob: glob::Paths,
-    current_reader: BufReader<fs::File>,
+    glob: Peekable<glob::Paths>,
+    current_path: PathBuf,
+    current_reader: Box<dyn BufRead>,
+    current_file: OpenedFile,
+    follow: Option<Duration>,
+    // Bytes read from the current file and any partially written line
+    position: u64,
+    pending: String,
+}
+
+/// Details about the file currently being read, used to detect truncation and rotation.
+struct OpenedFile {
+    id: Option<u64>,
+    compression: Compression,
 }
 
 impl FileSource {
@@ -23,9 +53,10 @@ impl FileSource {
                 require_literal_separator: false,
                 require_literal_leading_dot: false,
             },
-        )?;
+        )?
+        .peekable();
         let first_path = match glob.next() {
-            Some(p) => p,
+            Some(p) => p?,
             None => {
                 return Err(error::Error {
                     reason: format!("The glob provided ({}) did not return any paths.", glob_str),
@@ -33,72 +64,160 @@ impl FileSource {
                 })
             }
         };
-        let file = fs::OpenOptions::new().read(true).open(first_path?)?;
-        let current_reader = BufReader::new(file);
+        let (current_file, current_reader) = Self::open(&first_path)?;
         Ok(Self {
             glob,
+            current_path: first_path,
             current_reader,
+            current_file,
+            follow: None,
+            position: 0,
+            pending: String::new(),
         })
     }
+    /// Keep reading the last file once the end is reached, checking for new data every `poll_interval`.
+    pub fn follow(mut self, poll_interval: Duration) -> Self {
+        self.follow = Some(poll_interval);
+        self
+    }
+    /// The path of the file currently being read.
+    pub fn current_path(&self) -> &Path {
+        &self.current_path
+    }
+    fn open(path: &Path) -> Result<(OpenedFile, Box<dyn BufRead>)> {
+        debug!("Opening file {}", path.display());
+        let file = fs::OpenOptions::new().read(true).open(path)?;
+        let id = file_id(&file.metadata()?);
+        let (compression, reader) = compression::decode_detected(file)?;
+        Ok((OpenedFile { id, compression }, reader))
+    }
+    fn switch_to(&mut self, path: PathBuf) -> Result<()> {
+        let (current_file, current_reader) = Self::open(&path)?;
+        self.current_path = path;
+        self.current_reader = current_reader;
+        self.current_file = current_file;
+        self.position = 0;
+        self.pending.clear();
+        Ok(())
+    }
+    /// Reopen the followed file if it has been rotated or truncated, returns true if it was reopened.
+    fn reopen_if_moved(&mut self) -> Result<bool> {
+        let metadata = match fs::metadata(&self.current_path) {
+            Ok(m) => m,
+            // Rotated away and not recreated yet
+            Err(_) => return Ok(false),
+        };
+        let id = file_id(&metadata);
+        if id.is_some() && id != self.current_file.id {
+            debug!(
+                "{} has been rotated, reopening",
+                self.current_path.display()
+            );
+        } else if self.current_file.compression == Compression::None
+            && metadata.len() < self.position
+        {
+            debug!(
+                "{} has been truncated, reopening",
+                self.current_path.display()
+            );
+        } else {
+            return Ok(false);
+        }
+        self.switch_to(self.current_path.clone())?;
+        Ok(true)
+    }
+    fn take_line(&mut self) -> Vec<u8> {
+        let line = self
+            .pending
+            .trim_end_matches(['\n', '\r'])
+            .as_bytes()
+            .to_vec();
+        self.pending.clear();
+        line
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<u64> {
+    None
 }
 
 impl Source for FileSource {
     fn read_data(&mut self) -> Result<Vec<u8>> {
-        let mut line = String::new();
-        match self.current_reader.read_line(&mut line) {
-            Ok(len) => {
-                debug!("Reading {} bytes from Stdin: \"{}\"", len, line);
-                // Return data
-                Ok(line.as_bytes().to_vec())
+        loop {
+            let len = self.current_reader.read_line(&mut self.pending)?;
+            self.position += len as u64;
+            if self.pending.ends_with('\n') {
+                let line = self.take_line();
+                if line.is_empty() {
+                    continue;
+                }
+                debug!(
+                    "Reading {} bytes from {}: \"{}\"",
+                    line.len(),
+                    self.current_path.display(),
+                    String::from_utf8_lossy(&line)
+                );
+                return Ok(line);
             }
-            Err(_e) => {
-                match self.glob.next() {
-                    Some(p) => {
-                        // Create a BufReader
-                        let file = fs::OpenOptions::new().read(true).open(p?)?;
-                        let mut reader = BufReader::new(file);
-                        // Read line
-                        let mut line = String::new();
-                        let len = reader.read_line(&mut line)?;
-                        debug!("Reading {} bytes from Stdin: \"{}\"", len, line);
-                        // Store reader
-                        self.current_reader = reader;
-                        // Return Data
-                        Ok(line.as_bytes().to_vec())
-                    }
-                    None => Ok(Vec::new()),
+            // Reached the end of the current file
+            if let (Some(interval), None) = (self.follow, self.glob.peek()) {
+                if self.reopen_if_moved()? {
+                    continue;
                 }
+                // Keep any partially written line until the rest of it arrives
+                thread::sleep(interval);
+                return Ok(Vec::new());
+            }
+            if !self.pending.is_empty() {
+                return Ok(self.take_line());
+            }
+            // Move on to the next path in the glob
+            match self.glob.next() {
+                Some(p) => self.switch_to(p?)?,
+                None => return Ok(Vec::new()),
             }
         }
+    }
+    fn origin(&self) -> Option<String> {
+        Some(self.current_path.display().to_string())
+    }
+    fn modified(&self) -> Option<DateTime<FixedOffset>> {
+        let modified = fs::metadata(&self.current_path).ok()?.modified().ok()?;
+        Some(DateTime::<Utc>::from(modified).into())
+    }
+}
 
-        //     if let Some(_) = &self.current_reader {
-        //         // Get reader
-        //         let mut reader = self.current_reader.unwrap();
-        //         // Read line
-        //         let mut line = String::new();
-        //         let len = reader.read_line(&mut line)?;
-        //         debug!("Reading {} bytes from Stdin: \"{}\"", len, line);
-        //         // Store reader
-        //         self.current_reader = Some(reader);
-        //         // Return Data
-        //         Ok(line.as_bytes().to_vec())
-        //     } else {
-        //         match self.glob.next() {
-        //             Some(p) => {
-        //                 // Create a BufReader
-        //                 let file = fs::OpenOptions::new().read(true).open(p?)?;
-        //                 let mut reader = BufReader::new(file);
-        //                 // Read line
-        //                 let mut line = String::new();
-        //                 let len = reader.read_line(&mut line)?;
-        //                 debug!("Reading {} bytes from Stdin: \"{}\"", len, line);
-        //                 // Store reader
-        //                 self.current_reader = Some(reader);
-        //                 // Return Data
-        //                 Ok(line.as_bytes().to_vec())
-        //             }
-        //             None => return Ok(Vec::new()),
-        //         }
-        //     }
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn follow_truncated_file() {
+        // Unique to the process so parallel test runs do not share the file
+        let path = std::env::temp_dir().join(format!("dta_follow_test_{}.log", std::process::id()));
+        fs::write(&path, "2020-01-01 12:00:00\n2020-01-01 13").unwrap();
+        let mut source = FileSource::new(path.to_str().unwrap(), true)
+            .unwrap()
+            .follow(Duration::from_millis(1));
+        assert_eq!(source.read_data().unwrap(), b"2020-01-01 12:00:00");
+        // The partial line is held back until it is complete
+        assert!(source.read_data().unwrap().is_empty());
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b":00:00\n")
+            .unwrap();
+        assert_eq!(source.read_data().unwrap(), b"2020-01-01 13:00:00");
+        fs::write(&path, "2020-01-01 14:00:00\n").unwrap();
+        assert_eq!(source.read_data().unwrap(), b"2020-01-01 14:00:00");
+        fs::remove_file(path).unwrap();
     }
 }
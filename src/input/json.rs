@@ -2,34 +2,68 @@
 //!
 //! The Json parser is used to read in data from a Json source and parse out a date time field to be used in aggregation.
 //!
+//! A timestamp split over several keys can be selected with a [`TimestampTemplate`] of `gjson` paths, such as `{date} {time} {tz?}`.
+//!
 //! Todo: Add nested field support (recursive function that runs when there is a '.' in the field string provided).
 use crate::{
     error::{Error, ErrorKind},
-    input::Parser,
+    input::{template::TimestampTemplate, Parser, Transform},
     Data, Result,
 };
 
 pub struct JsonParser {
     field: String,
+    template: Option<TimestampTemplate>,
 }
 
 impl JsonParser {
-    pub fn new(field: &str) -> Self {
-        Self {
+    /// Create a `JsonParser`, the field is either a `gjson` path or a template of several paths.
+    pub fn new(field: &str) -> Result<Self> {
+        let template = match TimestampTemplate::is_template(field) {
+            true => Some(TimestampTemplate::new(field)?),
+            false => None,
+        };
+        Ok(Self {
             field: field.to_string(),
+            template,
+        })
+    }
+    /// Read the timestamp string from a JSON document.
+    fn timestamp(&self, data: &str) -> Result<String> {
+        let template = match &self.template {
+            Some(t) => t,
+            None => return Ok(gjson::get(data, &self.field).str().to_string()),
+        };
+        let lookup = |path: &str| {
+            let value = gjson::get(data, path);
+            match value.exists() {
+                true => Some(value.str().to_string()),
+                false => None,
+            }
+        };
+        match template.render(lookup) {
+            Some(ts_str) => Ok(ts_str),
+            None => Err(Error {
+                reason: format!(
+                    "Timestamp ({}) could not be read from: {}",
+                    self.field, data
+                ),
+                kind: ErrorKind::Parser,
+            }),
         }
     }
 }
 
 impl Parser for JsonParser {
     fn parse_data(
-        &self,
+        &mut self,
         raw: Vec<u8>,
         // field: Option<&String>,
         fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
         tz: Option<&String>,
         dict: Option<&mut crate::FormatDictionary>,
-        transform: Option<&String>,
+        transform: Option<&Transform>,
     ) -> Result<Data> {
         // Parse raw data back into a string
         use std::str;
@@ -45,12 +79,18 @@ impl Parser for JsonParser {
             }
         };
 
-        let value = gjson::get(&data, &self.field);
-        let ts_str = value.str();
+        let ts_str = self.timestamp(data)?;
         let mut data = match dict {
-            Some(mut d) => Data::from_dict(&ts_str, data.as_bytes().to_vec(), tz, &mut d)?,
-            None => Data::new(&ts_str, fmt, tz, data.as_bytes().to_vec())?,
+            Some(d) => Data::from_dict(&ts_str, data.as_bytes().to_vec(), epoch, tz, d)?,
+            None => Data::new(&ts_str, fmt, epoch, tz, data.as_bytes().to_vec())?,
         };
+        if let (Some(_), Some(_)) = (transform, &self.template) {
+            return Err(Error {
+                reason: "A transform cannot be applied to a timestamp built from several fields"
+                    .to_string(),
+                kind: ErrorKind::Parser,
+            });
+        }
         // If transform exists modify the value enum and
         match (
             transform,
@@ -58,7 +98,7 @@ impl Parser for JsonParser {
         ) {
             (Some(t), Ok(mut v)) => match v.get_mut(&self.field) {
                 Some(v_mut) => {
-                    let dt = data.timestamp.format(t).to_string();
+                    let dt = t.apply(&data.timestamp);
                     *v_mut = serde_json::Value::String(dt);
                     data.raw = serde_json::to_string(&v)?.as_bytes().to_vec();
                     Ok(data)
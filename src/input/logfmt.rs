@@ -0,0 +1,204 @@
+//! # Logfmt Parser
+//!
+//! The logfmt parser is used to read in `key=value` data (`ts=2021-03-01T10:00:00Z level=info msg="hello world"`) and parse out the timestamp held by a key.
+//!
+//! Quoted values can hold spaces and escaped quotes. A timestamp split over several keys can be selected with a [`TimestampTemplate`], such as `{date} {time}`.
+//!
+
+use crate::{
+    error::{Error, ErrorKind},
+    input::{template::TimestampTemplate, Parser, Transform},
+    Data, Result,
+};
+use std::ops::Range;
+
+/// A key and value read from a logfmt line, `range` is the position of the value (including any quotes) in the line.
+#[derive(Debug)]
+pub struct Pair<'a> {
+    pub key: &'a str,
+    pub value: String,
+    pub range: Range<usize>,
+    pub quoted: bool,
+}
+
+/// Split a logfmt line into its key value pairs, keys without a value have an empty value.
+pub fn pairs(line: &str) -> Vec<Pair<'_>> {
+    let bytes = line.as_bytes();
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let key_start = i;
+        while i < bytes.len() && bytes[i] != b'=' && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let key = &line[key_start..i];
+        if i >= bytes.len() || bytes[i] != b'=' {
+            pairs.push(Pair {
+                key,
+                value: String::new(),
+                range: i..i,
+                quoted: false,
+            });
+            continue;
+        }
+        i += 1;
+        let value_start = i;
+        let mut value = String::new();
+        let quoted = i < bytes.len() && bytes[i] == b'"';
+        if quoted {
+            i += 1;
+            let mut escaped = false;
+            for c in line[i..].chars() {
+                i += c.len_utf8();
+                match (escaped, c) {
+                    (false, '\\') => escaped = true,
+                    (false, '"') => break,
+                    (_, c) => {
+                        value.push(c);
+                        escaped = false;
+                    }
+                }
+            }
+        } else {
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            value.push_str(&line[value_start..i]);
+        }
+        pairs.push(Pair {
+            key,
+            value,
+            range: value_start..i,
+            quoted,
+        });
+    }
+    pairs
+}
+
+pub struct LogfmtParser {
+    key: String,
+    template: Option<TimestampTemplate>,
+}
+
+impl LogfmtParser {
+    /// Create a `LogfmtParser`, the key is either the key holding the timestamp or a template of several keys.
+    pub fn new(key: &str) -> Result<Self> {
+        let template = match TimestampTemplate::is_template(key) {
+            true => Some(TimestampTemplate::new(key)?),
+            false => None,
+        };
+        Ok(Self {
+            key: key.to_string(),
+            template,
+        })
+    }
+}
+
+impl Parser for LogfmtParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        // Parse raw data back into a string
+        use std::str;
+        let raw_data = match str::from_utf8(&raw[..]) {
+            Ok(d) => d,
+            Err(e) => {
+                let err = Error {
+                    reason: format!("Data coverted: {}", e),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let pairs = pairs(raw_data);
+        let lookup = |key: &str| pairs.iter().find(|p| p.key == key).map(|p| p.value.clone());
+        let ts_str = match &self.template {
+            Some(t) => t.render(lookup),
+            None => lookup(&self.key),
+        };
+        let ts_str = match ts_str {
+            Some(ts_str) => ts_str,
+            None => {
+                let err = Error {
+                    reason: format!(
+                        "Timestamp ({}) could not be read from: {}",
+                        self.key, raw_data
+                    ),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let mut data = match dict {
+            Some(d) => Data::from_dict(&ts_str, raw.clone(), epoch, tz, d)?,
+            None => Data::new(&ts_str, fmt, epoch, tz, raw.clone())?,
+        };
+        if let Some(t) = transform {
+            let pair =
+                match (&self.template, pairs.iter().find(|p| p.key == self.key)) {
+                    (None, Some(p)) => p,
+                    _ => return Err(Error {
+                        reason:
+                            "A transform cannot be applied to a timestamp built from several keys"
+                                .to_string(),
+                        kind: ErrorKind::Parser,
+                    }),
+                };
+            let dt = t.apply(&data.timestamp);
+            // Keep the value quoted if it was, or if it now needs to be
+            let value = match pair.quoted || dt.contains(|c: char| c.is_whitespace() || c == '"') {
+                true => format!("\"{}\"", dt.replace('\\', "\\\\").replace('"', "\\\"")),
+                false => dt,
+            };
+            let mut new = raw_data.to_string();
+            new.replace_range(pair.range.clone(), &value);
+            data.raw = new.as_bytes().to_vec();
+        }
+        debug!("Parsed data from raw bytes: {:?}", data);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn read_pairs() {
+        let pairs = pairs(r#"ts=2021-03-01T10:00:00Z msg="say \"hi\"" debug"#);
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(pairs[1].key, "msg");
+        assert_eq!(pairs[1].value, r#"say "hi""#);
+        assert_eq!(pairs[2].key, "debug");
+    }
+    #[test]
+    fn transform_quoted_key() {
+        let mut parser = LogfmtParser::new("time").unwrap();
+        let data = parser
+            .parse_data(
+                br#"level=info time="2021-03-01 10:00:00" msg=started"#.to_vec(),
+                None,
+                None,
+                None,
+                None,
+                Some(&Transform::new("%s")),
+            )
+            .unwrap();
+        assert_eq!(
+            data.as_string().unwrap(),
+            r#"level=info time="1614592800" msg=started"#
+        );
+    }
+}
@@ -6,37 +6,109 @@
 //! If no file input is select data is read line by line from standard input
 
 // Add in CSV and JSON inputs if feature selected
+pub mod access;
+pub mod archive;
+pub mod bodyfile;
+pub mod compression;
 pub mod csv;
 pub mod file;
 pub mod json;
+pub mod logfmt;
+pub mod plaso;
+pub mod regex;
 pub mod stdin;
+pub mod syslog;
+pub mod template;
+pub mod w3c;
 
 // Uses
-use crate::{Data, Result};
+use crate::{Data, Result, Timezone};
+use chrono::{DateTime, FixedOffset, Utc};
 
 /// Source Trait can be used to read in raw bytes, the struct the trait is implimented on holds the position.
 pub trait Source {
     /// Read an entry from source location
     fn read_data(&mut self) -> Result<Vec<u8>>;
+    /// Where the last entry was read from, such as a file path or archive member.
+    fn origin(&self) -> Option<String> {
+        None
+    }
+    /// When the origin was last modified, if the source knows.
+    fn modified(&self) -> Option<DateTime<FixedOffset>> {
+        None
+    }
+}
+
+/// Rewrites the timestamp of an entry in its raw data using a datetime format, see [`Parser::parse_data`].
+#[derive(Debug, Clone)]
+pub struct Transform {
+    format: String,
+    timezone: Option<Timezone>,
+}
+
+impl Transform {
+    pub fn new(format: &str) -> Self {
+        Self {
+            format: format.to_string(),
+            timezone: None,
+        }
+    }
+    /// Write timestamps in a timezone rather than the offset they were read with.
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = Some(timezone);
+        self
+    }
+    /// Format a timestamp.
+    pub fn apply(&self, timestamp: &DateTime<FixedOffset>) -> String {
+        match self.timezone {
+            Some(tz) => tz.from_utc(&timestamp.with_timezone(&Utc)),
+            None => *timestamp,
+        }
+        .format(&self.format)
+        .to_string()
+    }
 }
 
 /// Parser Trait can be implimented to read in raw data from a [`Source`](crate::input::Source) using an option provided
 pub trait Parser {
     /// Read an entry from source location.
+    /// Entries that hold no data (such as a header row) return an error of kind [`Skipped`](crate::error::ErrorKind::Skipped).
     fn parse_data(
-        &self,
+        &mut self,
         raw: Vec<u8>,
         fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
         tz: Option<&String>,
         dict: Option<&mut crate::FormatDictionary>,
-        transform: Option<&String>,
+        transform: Option<&Transform>,
     ) -> Result<Data>;
+    /// Read every entry held by the raw data, formats such as bodyfiles hold several timestamps in a single row.
+    /// By default this is the single entry read by [`parse_data`](Parser::parse_data).
+    fn parse_entries(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Vec<Data>> {
+        Ok(vec![self.parse_data(raw, fmt, epoch, tz, dict, transform)?])
+    }
+    /// The header of the data being parsed, if the format has one.
+    fn header(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Called when the [`Source`](crate::input::Source) moves on to a new origin (a file or archive member), so parsers that keep state can reset it.
+    fn set_origin(&mut self, _origin: Option<&str>) {}
+    /// Called after [`set_origin`](Parser::set_origin) with when the new origin was last modified, see [`Source::modified`](crate::input::Source::modified).
+    fn set_modified(&mut self, _modified: Option<DateTime<FixedOffset>>) {}
 }
 
 pub mod simple {
     use crate::{
         error::{Error, ErrorKind},
-        input::Parser,
+        input::{Parser, Transform},
         Data, Result,
     };
 
@@ -49,24 +121,25 @@ pub mod simple {
     }
     impl Parser for SimpleParser {
         fn parse_data(
-            &self,
+            &mut self,
             raw: Vec<u8>,
             fmt: Option<&String>,
+            epoch: Option<crate::EpochKind>,
             tz: Option<&String>,
             dict: Option<&mut crate::FormatDictionary>,
-            transform: Option<&String>,
+            transform: Option<&Transform>,
         ) -> Result<Data> {
             // Parse raw data back into a string
             use std::str;
             match str::from_utf8(&raw) {
                 Ok(t) => {
                     let mut data = match dict {
-                        Some(d) => Data::from_dict(&t, raw.clone(), tz, d)?,
-                        None => Data::new(&t, fmt, tz, raw.clone())?,
+                        Some(d) => Data::from_dict(t, raw.clone(), epoch, tz, d)?,
+                        None => Data::new(t, fmt, epoch, tz, raw.clone())?,
                     };
                     // If transform exists modify the data
                     if let Some(t) = transform {
-                        let dt = data.timestamp.format(t).to_string();
+                        let dt = t.apply(&data.timestamp);
                         data.raw = dt.as_bytes().to_vec();
                     }
                     debug!("Parsed data from raw bytes: {:?}", data);
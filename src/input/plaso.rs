@@ -0,0 +1,167 @@
+//! # Plaso Parser
+//!
+//! The Plaso parser reads super timelines written by Plaso's `psort`, using a [`CsvParser`] that knows the columns of each layout.
+//!
+//! The `l2tcsv` layout splits the timestamp over `date` (`MM/DD/YYYY`), `time` and `timezone` columns, the `MACB` column is used as the tag of each entry.
+//! The `dynamic` layout has an ISO 8601 `datetime` column, the `timestamp_desc` column is used as the tag of each entry.
+//! Both layouts always start with a header row.
+//!
+
+use crate::{
+    error::{Error, ErrorKind},
+    input::{csv::CsvParser, Parser, Transform},
+    Data, Result,
+};
+
+const L2TCSV_FORMAT: &str = "%m/%d/%Y %H:%M:%S";
+
+/// The CSV layouts written by `psort`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlasoLayout {
+    L2tCsv,
+    Dynamic,
+}
+
+impl PlasoLayout {
+    pub fn new(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "l2tcsv" => Ok(PlasoLayout::L2tCsv),
+            "dynamic" => Ok(PlasoLayout::Dynamic),
+            _ => Err(Error {
+                reason: format!(
+                    "Unknown Plaso layout \"{}\", layouts available: l2tcsv, dynamic",
+                    s
+                ),
+                kind: ErrorKind::Parser,
+            }),
+        }
+    }
+    /// The column (or template of columns) holding the timestamp.
+    fn column(&self) -> &'static str {
+        match self {
+            PlasoLayout::L2tCsv => "{date} {time}",
+            PlasoLayout::Dynamic => "datetime",
+        }
+    }
+    /// The column used to tag each entry.
+    fn tag_column(&self) -> &'static str {
+        match self {
+            PlasoLayout::L2tCsv => "MACB",
+            PlasoLayout::Dynamic => "timestamp_desc",
+        }
+    }
+}
+
+pub struct PlasoParser {
+    layout: PlasoLayout,
+    csv: CsvParser,
+}
+
+impl PlasoParser {
+    /// Create a `PlasoParser` for one of the `psort` CSV layouts.
+    pub fn new(layout: PlasoLayout) -> Result<Self> {
+        Ok(Self {
+            layout,
+            csv: CsvParser::new(layout.column())?.has_headers(true),
+        })
+    }
+}
+
+impl Parser for PlasoParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        _dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        let record = match std::str::from_utf8(&raw[..]) {
+            Ok(d) => self.csv.read_record(d)?,
+            Err(_) => None,
+        };
+        // The timezone column of l2tcsv takes priority over the one provided
+        let row_tz = match (self.layout, &record) {
+            (PlasoLayout::L2tCsv, Some(r)) => {
+                self.csv.field(r, "timezone").map(|t| match t.trim() {
+                    "UTC" | "GMT" => "+00:00".to_string(),
+                    t => t.to_string(),
+                })
+            }
+            _ => None,
+        };
+        let l2tcsv_format = L2TCSV_FORMAT.to_string();
+        let fmt = match self.layout {
+            PlasoLayout::L2tCsv => Some(fmt.unwrap_or(&l2tcsv_format)),
+            PlasoLayout::Dynamic => fmt,
+        };
+        // The layouts have fixed formats so the format dictionary is not used
+        let mut data =
+            self.csv
+                .parse_data(raw, fmt, epoch, row_tz.as_ref().or(tz), None, transform)?;
+        data.tag = record
+            .as_ref()
+            .and_then(|r| self.csv.field(r, self.layout.tag_column()))
+            .map(|t| t.trim().to_string());
+        Ok(data)
+    }
+    fn header(&self) -> Option<Vec<u8>> {
+        self.csv.header()
+    }
+    fn set_origin(&mut self, origin: Option<&str>) {
+        self.csv.set_origin(origin);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn parse_l2tcsv() {
+        let mut parser = PlasoParser::new(PlasoLayout::L2tCsv).unwrap();
+        let header = b"date,time,timezone,MACB,source,sourcetype,type,user,host,short,desc,version,filename,inode,notes,format,extra";
+        let header = parser.parse_data(header.to_vec(), None, None, None, None, None);
+        assert!(matches!(header.unwrap_err().kind, ErrorKind::Skipped));
+        let data = parser
+            .parse_data(
+                b"03/01/2021,10:00:00,UTC,M...,FILE,OS mtime,Modified,-,host,/a,/a,2,/a,12,-,filestat,-".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2021-03-01T10:00:00+00:00");
+        assert_eq!(data.tag.as_deref(), Some("M..."));
+    }
+    #[test]
+    fn parse_dynamic() {
+        let mut parser = PlasoParser::new(PlasoLayout::Dynamic).unwrap();
+        assert!(parser
+            .parse_data(
+                b"datetime,timestamp_desc,source,source_long,message,parser,display_name,tag"
+                    .to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None
+            )
+            .is_err());
+        let data = parser
+            .parse_data(
+                b"2021-03-01T10:00:00.000000+00:00,Content Modification Time,FILE,File stat,/a,filestat,OS:/a,-".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2021-03-01T10:00:00+00:00");
+        assert_eq!(data.tag.as_deref(), Some("Content Modification Time"));
+    }
+}
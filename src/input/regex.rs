@@ -0,0 +1,198 @@
+//! # Regex Parser
+//!
+//! The Regex parser is used to pull a timestamp out of any line of text using a regular expression.
+//!
+//! The pattern must have a named `ts` capture group holding the timestamp, an optional `tz` group is used as the timezone for timestamps without one.
+//! The full line is kept as the raw data. Common layouts are available as presets with [`RegexParser::preset`], some presets also supply a datetime format.
+//!
+
+use crate::{
+    error::{Error, ErrorKind},
+    input::{Parser, Transform},
+    Data, Result,
+};
+use ::regex::Regex;
+
+/// Named patterns that can be used in place of a regular expression, with the datetime format used when none is provided.
+pub const PRESETS: &[(&str, &str, Option<&str>)] = &[
+    (
+        "iso8601",
+        r"(?P<ts>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?)(?P<tz>Z|[+-]\d{2}:?\d{2})?",
+        None,
+    ),
+    (
+        "python",
+        r"^(?P<ts>\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2},\d{3})",
+        Some("%Y-%m-%d %H:%M:%S,%3f"),
+    ),
+    (
+        "bracketed",
+        r"\[(?P<ts>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?)(?P<tz>Z|[+-]\d{2}:?\d{2})?\]",
+        None,
+    ),
+    (
+        "us",
+        r"(?P<ts>\d{1,2}/\d{1,2}/\d{4} \d{1,2}:\d{2}:\d{2} [AP]M)",
+        Some("%m/%d/%Y %I:%M:%S %p"),
+    ),
+    ("epoch", r"\b(?P<ts>\d{10})\b", None),
+];
+
+pub struct RegexParser {
+    regex: Regex,
+    fmt: Option<String>,
+}
+
+impl RegexParser {
+    /// Create a `RegexParser` from a pattern that has a named `ts` group.
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)?;
+        if !regex.capture_names().any(|n| n == Some("ts")) {
+            return Err(Error {
+                reason: format!("Pattern \"{}\" has no named \"ts\" capture group", pattern),
+                kind: ErrorKind::Parser,
+            });
+        }
+        Ok(Self { regex, fmt: None })
+    }
+    /// Create a `RegexParser` from one of the named [`PRESETS`].
+    pub fn preset(name: &str) -> Result<Self> {
+        match PRESETS.iter().find(|(n, _, _)| *n == name) {
+            Some((_, pattern, fmt)) => {
+                let mut parser = Self::new(pattern)?;
+                parser.fmt = fmt.map(str::to_string);
+                Ok(parser)
+            }
+            None => Err(Error {
+                reason: format!(
+                    "Unknown regex preset \"{}\", presets available: {}",
+                    name,
+                    PRESETS
+                        .iter()
+                        .map(|(n, _, _)| *n)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ),
+                kind: ErrorKind::Parser,
+            }),
+        }
+    }
+    /// Create a `RegexParser` from a preset name if there is one, otherwise from a pattern.
+    pub fn from_pattern_or_preset(s: &str) -> Result<Self> {
+        match PRESETS.iter().any(|(n, _, _)| *n == s) {
+            true => Self::preset(s),
+            false => Self::new(s),
+        }
+    }
+}
+
+impl Parser for RegexParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        // Parse raw data back into a string
+        use std::str;
+        let raw_data = match str::from_utf8(&raw[..]) {
+            Ok(d) => d,
+            Err(e) => {
+                let err = Error {
+                    reason: format!("Data coverted: {}", e),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let captures = match self.regex.captures(raw_data) {
+            Some(c) => c,
+            None => {
+                let err = Error {
+                    reason: format!("Pattern did not match: {}", raw_data),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let ts = match captures.name("ts") {
+            Some(ts) => ts,
+            None => {
+                return Err(Error {
+                    reason: format!("No timestamp captured from: {}", raw_data),
+                    kind: ErrorKind::Parser,
+                })
+            }
+        };
+        // A captured timezone takes priority over the one provided
+        let captured_tz = captures.name("tz").map(|m| match m.as_str() {
+            "Z" | "z" | "UTC" | "GMT" => "+00:00".to_string(),
+            t => t.to_string(),
+        });
+        let tz = captured_tz.as_ref().or(tz);
+        let fmt = fmt.or(self.fmt.as_ref());
+        let mut data = match dict {
+            Some(d) => Data::from_dict(ts.as_str(), raw.clone(), epoch, tz, d)?,
+            None => Data::new(ts.as_str(), fmt, epoch, tz, raw.clone())?,
+        };
+        if let Some(t) = transform {
+            let dt = t.apply(&data.timestamp);
+            let mut new = raw_data.to_string();
+            new.replace_range(ts.range(), &dt);
+            data.raw = new.as_bytes().to_vec();
+        }
+        debug!("Parsed data from raw bytes: {:?}", data);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn parse_with_preset() {
+        let mut parser = RegexParser::preset("python").unwrap();
+        let raw = b"2021-03-01 10:00:00,123 INFO started".to_vec();
+        let data = parser
+            .parse_data(raw.clone(), None, None, None, None, None)
+            .unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2021-03-01T10:00:00.123+00:00");
+        assert_eq!(data.raw, raw);
+        assert!(RegexParser::new(r"(\d+)").is_err());
+    }
+    #[test]
+    fn parse_with_timezone_group() {
+        let mut parser = RegexParser::preset("iso8601").unwrap();
+        let data = parser
+            .parse_data(
+                b"host=a at 2021-03-01T10:00:00Z msg=x".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                Some(&Transform::new("%s")),
+            )
+            .unwrap();
+        assert_eq!(data.as_string().unwrap(), "host=a at 1614592800Z msg=x");
+        // Transformed timestamps are written in the output timezone
+        let transform =
+            Transform::new("%H:%M%:z").timezone(crate::Timezone::new("Europe/Paris").unwrap());
+        let data = parser
+            .parse_data(
+                b"at 2021-03-01T10:00:00Z".to_vec(),
+                None,
+                None,
+                None,
+                None,
+                Some(&transform),
+            )
+            .unwrap();
+        assert_eq!(data.as_string().unwrap(), "at 11:00+01:00Z");
+    }
+}
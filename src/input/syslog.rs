@@ -0,0 +1,236 @@
+//! # Syslog Parser
+//!
+//! The Syslog parser reads the timestamp from RFC 3164 (`Jan  5 03:12:01 host sshd[42]: ...`) and RFC 5424 (`<34>1 2003-10-11T22:14:15.003Z host ...`) messages.
+//!
+//! RFC 3164 timestamps have no year, it is inferred from an anchor: the anchor provided to [`SyslogParser::new`], otherwise the modification time of the file or archive member being read, otherwise the current time.
+//! A `Feb 29` timestamp is placed in the most recent leap year, the following timestamps carry on in the year inferred before it.
+//! The year is chosen so the first timestamp is not after the anchor, after that the year is incremented whenever December lines are followed by January lines.
+//! Entries with an inferred year are flagged as [`inferred`](crate::Data::inferred).
+//!
+
+use crate::{
+    data::parsing,
+    error::{Error, ErrorKind},
+    input::{Parser, Transform},
+    AmbiguousTime, Data, Result,
+};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Utc};
+
+lazy_static! {
+    static ref RFC_5424_REGEX: regex::Regex =
+        regex::Regex::new(r"^(?:<\d{1,3}>)?\d{1,2} (?P<ts>\S+) ").unwrap();
+    static ref RFC_3164_REGEX: regex::Regex = regex::Regex::new(
+        r"^(?:<\d{1,3}>)?(?P<ts>(?P<month>[A-Z][a-z]{2}) {1,2}(?P<day>\d{1,2}) (?P<time>\d{2}:\d{2}:\d{2}(?:\.\d+)?))"
+    )
+    .unwrap();
+}
+
+const RFC_3164_FORMAT: &str = "%Y %b %d %H:%M:%S%.f";
+
+pub struct SyslogParser {
+    anchor: Option<DateTime<FixedOffset>>,
+    file_anchor: Option<DateTime<FixedOffset>>,
+    year: Option<i32>,
+    last_month: Option<u32>,
+}
+
+impl SyslogParser {
+    /// Create a `SyslogParser`, the anchor is used to infer the year of RFC 3164 timestamps.
+    pub fn new(anchor: Option<DateTime<FixedOffset>>) -> Self {
+        Self {
+            anchor,
+            file_anchor: None,
+            year: None,
+            last_month: None,
+        }
+    }
+    fn parse_rfc_3164(
+        &mut self,
+        captures: &regex::Captures,
+        tz: Option<&String>,
+        ambiguous: AmbiguousTime,
+    ) -> Result<DateTime<FixedOffset>> {
+        let leap_day = &captures["month"] == "Feb" && captures["day"].parse() == Ok(29);
+        let timestamp = |mut year: i32| {
+            // February 29th can only be in a leap year, so go back to the last one
+            while leap_day && NaiveDate::from_ymd_opt(year, 2, 29).is_none() {
+                year -= 1;
+            }
+            let s = format!(
+                "{} {} {} {}",
+                year, &captures["month"], &captures["day"], &captures["time"]
+            );
+            let fmt = RFC_3164_FORMAT.to_string();
+            parsing::parse_naive_dt_as(&s, Some(&fmt), tz, ambiguous).ok_or(Error {
+                reason: format!("{} cannot be parsed as a syslog timestamp", &captures["ts"]),
+                kind: ErrorKind::DateTime,
+            })
+        };
+        let mut year = match self.year {
+            Some(year) => year,
+            None => {
+                // Pick the year that keeps the first timestamp from being after the anchor
+                let anchor = self
+                    .anchor
+                    .or(self.file_anchor)
+                    .unwrap_or_else(|| Utc::now().into());
+                let year = anchor.year();
+                match timestamp(year)? > anchor + Duration::days(1) {
+                    true => year - 1,
+                    false => year,
+                }
+            }
+        };
+        let mut dt = timestamp(year)?;
+        if let Some(last_month) = self.last_month {
+            if last_month >= dt.month() + 6 {
+                debug!("Syslog year rolled over from {} to {}", year, year + 1);
+                year += 1;
+                dt = timestamp(year)?;
+            }
+        }
+        // The year before any leap day adjustment, so it does not carry on to later timestamps
+        self.year = Some(year);
+        self.last_month = Some(dt.month());
+        Ok(dt)
+    }
+}
+
+impl Parser for SyslogParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        // Parse raw data back into a string
+        use std::str;
+        let raw_data = match str::from_utf8(&raw[..]) {
+            Ok(d) => d,
+            Err(e) => {
+                let err = Error {
+                    reason: format!("Data coverted: {}", e),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        let ambiguous = dict
+            .as_ref()
+            .map(|d| d.ambiguous_time())
+            .unwrap_or_default();
+        let (timestamp, range, inferred) = if let Some(captures) = RFC_5424_REGEX.captures(raw_data)
+        {
+            let ts = &captures["ts"];
+            let timestamp = match dict {
+                Some(d) => Data::from_dict(ts, Vec::new(), epoch, tz, d)?.timestamp,
+                None => Data::new(ts, fmt, epoch, tz, Vec::new())?.timestamp,
+            };
+            (timestamp, captures.name("ts").unwrap().range(), false)
+        } else if let Some(captures) = RFC_3164_REGEX.captures(raw_data) {
+            (
+                self.parse_rfc_3164(&captures, tz, ambiguous)?,
+                captures.name("ts").unwrap().range(),
+                true,
+            )
+        } else {
+            let err = Error {
+                reason: format!("Not a syslog message: {}", raw_data),
+                kind: ErrorKind::Parser,
+            };
+            error!("Error occured during parsing: {:?}", err);
+            return Err(err);
+        };
+        let mut data = Data {
+            timestamp,
+            raw: raw.clone(),
+            origin: None,
+            tag: None,
+            inferred,
+        };
+        if let Some(t) = transform {
+            let dt = t.apply(&data.timestamp);
+            let mut new = raw_data.to_string();
+            new.replace_range(range, &dt);
+            data.raw = new.as_bytes().to_vec();
+        }
+        debug!("Parsed data from raw bytes: {:?}", data);
+        Ok(data)
+    }
+    /// Each file is anchored by its own modification time, so the inferred year starts again.
+    fn set_origin(&mut self, _origin: Option<&str>) {
+        self.year = None;
+        self.last_month = None;
+        self.file_anchor = None;
+    }
+    fn set_modified(&mut self, modified: Option<DateTime<FixedOffset>>) {
+        self.file_anchor = modified;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    fn parse(parser: &mut SyslogParser, line: &str) -> String {
+        parser
+            .parse_data(line.as_bytes().to_vec(), None, None, None, None, None)
+            .unwrap()
+            .timestamp
+            .to_rfc3339()
+    }
+    #[test]
+    fn infer_year_with_rollover() {
+        let anchor = DateTime::parse_from_rfc3339("2021-01-10T00:00:00+00:00").unwrap();
+        let mut parser = SyslogParser::new(Some(anchor));
+        assert_eq!(
+            parse(&mut parser, "Dec 31 23:59:59 host sshd[1]: a"),
+            "2020-12-31T23:59:59+00:00"
+        );
+        assert_eq!(
+            parse(&mut parser, "Jan  1 00:00:01 host sshd[1]: b"),
+            "2021-01-01T00:00:01+00:00"
+        );
+    }
+    #[test]
+    fn infer_leap_year() {
+        let anchor = DateTime::parse_from_rfc3339("2023-06-01T00:00:00+00:00").unwrap();
+        let mut parser = SyslogParser::new(Some(anchor));
+        assert_eq!(
+            parse(&mut parser, "Feb 29 12:00:00 host sshd[1]: a"),
+            "2020-02-29T12:00:00+00:00"
+        );
+    }
+    #[test]
+    fn leap_day_keeps_the_inferred_year() {
+        let anchor = DateTime::parse_from_rfc3339("2023-06-01T00:00:00+00:00").unwrap();
+        let mut parser = SyslogParser::new(Some(anchor));
+        assert_eq!(
+            parse(&mut parser, "Feb 28 12:00:00 host sshd[1]: a"),
+            "2023-02-28T12:00:00+00:00"
+        );
+        assert_eq!(
+            parse(&mut parser, "Feb 29 12:00:00 host sshd[1]: b"),
+            "2020-02-29T12:00:00+00:00"
+        );
+        assert_eq!(
+            parse(&mut parser, "Mar  1 12:00:00 host sshd[1]: c"),
+            "2023-03-01T12:00:00+00:00"
+        );
+    }
+    #[test]
+    fn parse_rfc_5424() {
+        let mut parser = SyslogParser::new(None);
+        assert_eq!(
+            parse(
+                &mut parser,
+                "<34>1 2003-10-11T22:14:15.003Z mymachine su - ID47 - message"
+            ),
+            "2003-10-11T22:14:15.003+00:00"
+        );
+    }
+}
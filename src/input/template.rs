@@ -0,0 +1,126 @@
+//! # Timestamp Templates
+//!
+//! Templates are used to build a timestamp from several fields, such as a date and a time held in separate CSV columns or JSON keys.
+//!
+//! Fields are written in braces and everything else is kept as is, so `{date} {time}` joins the `date` and `time` fields with a space.
+//! A field ending with `?` is optional and is left empty when it is missing, for example `{date}T{time}{tz?}`.
+//! Field names cannot hold whitespace, commas, colons or quotes, so a `gjson` multipath such as `{a,b}` is still read as a single path.
+//!
+
+use crate::{
+    error::{Error, ErrorKind},
+    Result,
+};
+
+lazy_static! {
+    static ref FIELD_REGEX: regex::Regex = regex::Regex::new(r#"\{[^{}\s,:"]+\}"#).unwrap();
+}
+
+#[derive(Debug, Clone)]
+enum TemplatePart {
+    Literal(String),
+    Field { name: String, optional: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct TimestampTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+impl TimestampTemplate {
+    /// Whether a field selection string should be read as a template rather than a single field, every brace has to be part of a `{name}` field.
+    pub fn is_template(s: &str) -> bool {
+        FIELD_REGEX.is_match(s) && !FIELD_REGEX.replace_all(s, "").contains(&['{', '}'][..])
+    }
+    pub fn new(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            let end = match rest[start..].find('}') {
+                Some(e) => start + e,
+                None => {
+                    return Err(Error {
+                        reason: format!("Template \"{}\" has an unclosed field", template),
+                        kind: ErrorKind::Parser,
+                    })
+                }
+            };
+            let field = &rest[start + 1..end];
+            let (name, optional) = match field.strip_suffix('?') {
+                Some(n) => (n, true),
+                None => (field, false),
+            };
+            if name.is_empty() {
+                return Err(Error {
+                    reason: format!("Template \"{}\" has an empty field", template),
+                    kind: ErrorKind::Parser,
+                });
+            }
+            parts.push(TemplatePart::Field {
+                name: name.to_string(),
+                optional,
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+    /// The names of the fields used by the template.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|p| match p {
+            TemplatePart::Field { name, .. } => Some(name.as_str()),
+            TemplatePart::Literal(_) => None,
+        })
+    }
+    /// Build the timestamp string using `lookup` to read each field, returns None if a required field is missing.
+    pub fn render<F: FnMut(&str) -> Option<String>>(&self, mut lookup: F) -> Option<String> {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(l) => rendered.push_str(l),
+                TemplatePart::Field { name, optional } => match (lookup(name), optional) {
+                    (Some(v), _) => rendered.push_str(v.trim()),
+                    (None, true) => (),
+                    (None, false) => return None,
+                },
+            }
+        }
+        Some(rendered.trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn render_template() {
+        let template = TimestampTemplate::new("{date}T{time}{tz?}").unwrap();
+        assert_eq!(
+            template.fields().collect::<Vec<_>>(),
+            ["date", "time", "tz"]
+        );
+        let rendered = template.render(|f| match f {
+            "date" => Some("2021-03-01".to_string()),
+            "time" => Some("10:00:00".to_string()),
+            _ => None,
+        });
+        assert_eq!(rendered, Some("2021-03-01T10:00:00".to_string()));
+        assert_eq!(template.render(|_| None), None);
+        assert!(TimestampTemplate::new("{date").is_err());
+    }
+    #[test]
+    fn detect_template() {
+        assert!(TimestampTemplate::is_template("{date} {time} {tz?}"));
+        assert!(TimestampTemplate::is_template("{event.date}T{event.time}"));
+        assert!(!TimestampTemplate::is_template("timestamp"));
+        assert!(!TimestampTemplate::is_template("{date,time}"));
+        assert!(!TimestampTemplate::is_template("{\"ts\":event.time}"));
+        assert!(!TimestampTemplate::is_template("{date} {a,b}"));
+    }
+}
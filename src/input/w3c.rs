@@ -0,0 +1,155 @@
+//! # W3C Extended Log Parser
+//!
+//! The W3C parser reads the timestamp from W3C Extended Log Format files, as written by IIS and many proxies.
+//!
+//! The columns are defined by `#Fields:` directives which can change part way through a file, the timestamp is built by joining the `date` and `time` fields.
+//! When there is no `date` field the date of the last `#Date:` directive is used. Directive lines are skipped rather than reported as errors.
+//! A transform cannot be applied as the timestamp is split over two fields.
+//!
+
+use crate::{
+    data::parsing,
+    error::{Error, ErrorKind},
+    input::{Parser, Transform},
+    Data, Result,
+};
+
+const W3C_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+#[derive(Default)]
+pub struct W3cParser {
+    fields: Vec<String>,
+    fields_line: Option<Vec<u8>>,
+    date: Option<String>,
+}
+
+impl W3cParser {
+    /// Handle a directive line, keeping track of the fields and date.
+    fn read_directive(&mut self, raw: &[u8], directive: &str) {
+        if let Some(fields) = directive.strip_prefix("#Fields:") {
+            self.fields = fields.split_whitespace().map(str::to_string).collect();
+            self.fields_line = Some(raw.to_vec());
+            debug!("W3C fields set to {:?}", self.fields);
+        } else if let Some(date) = directive.strip_prefix("#Date:") {
+            self.date = date.split_whitespace().next().map(str::to_string);
+        }
+    }
+    fn field<'a>(&self, values: &[&'a str], name: &str) -> Option<&'a str> {
+        let index = self.fields.iter().position(|f| f == name)?;
+        values.get(index).copied()
+    }
+}
+
+impl Parser for W3cParser {
+    fn parse_data(
+        &mut self,
+        raw: Vec<u8>,
+        fmt: Option<&String>,
+        epoch: Option<crate::EpochKind>,
+        tz: Option<&String>,
+        dict: Option<&mut crate::FormatDictionary>,
+        transform: Option<&Transform>,
+    ) -> Result<Data> {
+        // Parse raw data back into a string
+        use std::str;
+        let raw_data = match str::from_utf8(&raw[..]) {
+            Ok(d) => d,
+            Err(e) => {
+                let err = Error {
+                    reason: format!("Data coverted: {}", e),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        if raw_data.starts_with('#') {
+            self.read_directive(&raw, raw_data);
+            return Err(Error {
+                reason: format!("Skipped W3C directive: {}", raw_data),
+                kind: ErrorKind::Skipped,
+            });
+        }
+        if self.fields.is_empty() {
+            let err = Error {
+                reason: format!("No #Fields directive before: {}", raw_data),
+                kind: ErrorKind::Parser,
+            };
+            error!("Error occured during parsing: {:?}", err);
+            return Err(err);
+        }
+        let values: Vec<&str> = raw_data.split_whitespace().collect();
+        let date = self.field(&values, "date").or(self.date.as_deref());
+        let time = self.field(&values, "time");
+        let ts_str = match (date, time) {
+            (Some(d), Some(t)) => format!("{} {}", d, t),
+            _ => {
+                let err = Error {
+                    reason: format!("No date and time fields in: {}", raw_data),
+                    kind: ErrorKind::Parser,
+                };
+                error!("Error occured during parsing: {:?}", err);
+                return Err(err);
+            }
+        };
+        // W3C timestamps are in UTC unless a timezone is provided
+        let w3c_format = W3C_FORMAT.to_string();
+        let timestamp = match dict {
+            Some(d) => Data::from_dict(&ts_str, Vec::new(), epoch, tz, d)?.timestamp,
+            None => match parsing::parse_naive_dt(&ts_str, Some(fmt.unwrap_or(&w3c_format)), tz) {
+                Some(t) => t,
+                None => {
+                    return Err(Error {
+                        reason: format!("{} cannot be parsed.", ts_str),
+                        kind: ErrorKind::DateTime,
+                    })
+                }
+            },
+        };
+        if transform.is_some() {
+            return Err(Error {
+                reason: "A transform cannot be applied to a timestamp built from several fields"
+                    .to_string(),
+                kind: ErrorKind::Parser,
+            });
+        }
+        let data = Data {
+            timestamp,
+            raw,
+            origin: None,
+            tag: None,
+            inferred: false,
+        };
+        debug!("Parsed data from raw bytes: {:?}", data);
+        Ok(data)
+    }
+    fn header(&self) -> Option<Vec<u8>> {
+        self.fields_line.clone()
+    }
+    /// Directives only apply to the file they are in.
+    fn set_origin(&mut self, _origin: Option<&str>) {
+        self.fields.clear();
+        self.date = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    fn parse(parser: &mut W3cParser, line: &str) -> Result<Data> {
+        parser.parse_data(line.as_bytes().to_vec(), None, None, None, None, None)
+    }
+    #[test]
+    fn fields_change_mid_file() {
+        let mut parser = W3cParser::default();
+        assert!(parse(&mut parser, "#Software: Microsoft IIS").is_err());
+        assert!(parse(&mut parser, "#Fields: date time cs-method").is_err());
+        let data = parse(&mut parser, "2021-03-01 10:00:00 GET").unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2021-03-01T10:00:00+00:00");
+        assert!(parse(&mut parser, "#Date: 2021-03-02 00:00:00").is_err());
+        assert!(parse(&mut parser, "#Fields: cs-method time").is_err());
+        let data = parse(&mut parser, "GET 11:30:00").unwrap();
+        assert_eq!(data.timestamp.to_rfc3339(), "2021-03-02T11:30:00+00:00");
+    }
+}
@@ -11,5 +11,10 @@ pub mod aggregators;
 mod data;
 mod error;
 pub mod input;
+pub mod sniff;
 
-pub use {data::parsing::FormatDictionary, data::Data, error::Result};
+pub use {
+    data::parsing::{AmbiguousTime, DateOrder, EpochKind, FormatDictionary, Timezone},
+    data::Data,
+    error::{Error, ErrorKind, Result},
+};
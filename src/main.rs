@@ -5,30 +5,53 @@ extern crate date_time_aggregator;
 extern crate simplelog;
 
 // Conditional Imports
+use date_time_aggregator::input::access::AccessLogParser;
+use date_time_aggregator::input::archive::ArchiveSource;
+use date_time_aggregator::input::bodyfile::BodyfileParser;
 use date_time_aggregator::input::csv::CsvParser;
 #[allow(unused_imports)]
 use date_time_aggregator::input::file::FileSource;
 use date_time_aggregator::input::json::JsonParser;
+use date_time_aggregator::input::logfmt::LogfmtParser;
+use date_time_aggregator::input::plaso::{PlasoLayout, PlasoParser};
+use date_time_aggregator::input::regex::RegexParser;
 use date_time_aggregator::input::stdin::StdinSource;
+use date_time_aggregator::input::syslog::SyslogParser;
+use date_time_aggregator::input::w3c::W3cParser;
+use date_time_aggregator::sniff::Report;
 // use date_time_aggregator::input::stdin::StdinSource;
 
 // Imports
+use chrono::{DateTime, FixedOffset, Utc};
 use date_time_aggregator::{
     aggregators::{
         count::{CountAggregator, CountsAggregator},
         max::{MaximumAggregator, MaximumsAggregator},
         min::{MinimumAggregator, MinimumsAggregator},
-        range::RangeAggregator,
+        range::{RangeAggregator, Window},
+        recurring::Recurring,
         split::SplitAggregator,
-        Aggregator,
+        Aggregator, Alignment, Increment,
     },
-    input::{simple::SimpleParser, Parser, Source},
+    input::{simple::SimpleParser, template::TimestampTemplate, Parser, Source, Transform},
+    AmbiguousTime, Data, DateOrder, EpochKind, Error, ErrorKind, FormatDictionary, Timezone,
 };
 use log::LevelFilter;
 use simplelog::*;
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use structopt::StructOpt;
 
+// How often a followed file is checked for new data
+const FOLLOW_POLL_MS: u64 = 250;
+// How often a snapshot of the aggregation is printed when following, unless --interval is given
+const SNAPSHOT_INTERVAL_SECS: u64 = 10;
+// How many entries are kept, in input order, from the first date that could be day or month first until the date order is found.
+// Past this the dates are read day first
+const PENDING_LIMIT: usize = 100_000;
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "dta", about = "A date time aggreator.")]
 struct Opt {
@@ -41,31 +64,141 @@ struct Opt {
     #[structopt(long = "directory", short = "R")]
     glob: Option<String>,
 
-    /// Provide either a YAML file or a directory containing YAML files (.yml) that match the FormatDictionary structure.
+    /// Read data from the members of a tar (optionally compressed) or zip archive. When selected Stdin and the input glob are ignored.
+    #[structopt(long = "archive", short = "A", parse(from_os_str))]
+    archive: Option<PathBuf>,
+
+    /// Only read archive members whose path matches this glob.
+    #[structopt(long = "members", requires = "archive")]
+    members: Option<String>,
+
+    /// Follow the last input file as it grows (like `tail -F`), printing a snapshot of the aggregation every interval.
+    #[structopt(long)]
+    follow: bool,
+
+    /// Seconds between snapshots of the aggregation when following input, 10 by default.
+    #[structopt(long = "interval", requires = "follow")]
+    interval: Option<u64>,
+
+    /// Provide either a YAML file or a directory containing YAML files (.yml or .yaml) that match the FormatDictionary structure.
+    /// Entries are added to the bundled formats, overriding formats with the same name (files in a directory are read in name order).
+    /// Each entry has a `fmt` and can declare a `tz`, an `epoch` in place of a format, or a `locale` of month and weekday names.
     #[structopt(long = "formats", short = "F")]
     formats: Option<PathBuf>,
 
-    /// Parse CSV data, supply a valid position for the timestamp field (starting at 0).
+    /// A file the order the formats are tried in is loaded from and saved to, so later runs start with the formats that matched most recently.
+    #[structopt(long, requires = "formats")]
+    priority: Option<PathBuf>,
+
+    /// Parse CSV data, supply either a valid position for the timestamp field (starting at 0) or the name of the field in the header row.
+    /// A timestamp split over several fields can be joined with a template such as "{date} {time}" or "{0}T{1}".
     #[structopt(short, long)]
-    csv: Option<u8>,
+    csv: Option<String>,
+
+    /// The CSV field delimiter, a single character or one of `tab`, `pipe`, `semicolon`.
+    #[structopt(long, default_value = ",", parse(try_from_str = parse_csv_char))]
+    delimiter: u8,
+
+    /// The CSV quote character.
+    #[structopt(long, default_value = "\"", parse(try_from_str = parse_csv_char))]
+    quote: u8,
+
+    /// Treat the first CSV row as a header. By default a header is detected when the first timestamp field can't be parsed.
+    #[structopt(long, conflicts_with = "no-headers")]
+    headers: bool,
+
+    /// Treat the first CSV row as data.
+    #[structopt(long)]
+    no_headers: bool,
 
     /// Parse JSON data, field has to be the field name. (`gjson` adds support for nested timestamp fields, use the `gjson` syntax)
+    /// A timestamp split over several fields can be joined with a template such as "{date} {time} {tz?}", `?` marks optional fields.
     #[structopt(short, long)]
     json: Option<String>,
 
+    /// Parse logfmt data (key=value pairs), supply the key holding the timestamp or a template such as "{date} {time}".
+    #[structopt(long)]
+    logfmt: Option<String>,
+
+    /// Parse any text using a regular expression with a named `ts` group (and optionally `tz`), or the name of a preset (iso8601, python, bracketed, us, epoch).
+    #[structopt(short = "x", long)]
+    regex: Option<String>,
+
+    /// Parse syslog messages (RFC 3164 and RFC 5424).
+    #[structopt(long)]
+    syslog: bool,
+
+    /// Parse web server access logs, supply `common`, `combined` or an nginx `log_format` string.
+    #[structopt(long)]
+    access: Option<String>,
+
+    /// Parse W3C Extended Log Format data (IIS and proxy logs) using its #Fields directives.
+    #[structopt(long)]
+    w3c: bool,
+
+    /// Parse Sleuth Kit bodyfiles, each row is expanded into an entry for each of its MACB timestamps.
+    #[structopt(long)]
+    bodyfile: bool,
+
+    /// Parse Plaso super timelines written by psort, supply the layout: `l2tcsv` or `dynamic`.
+    #[structopt(long)]
+    plaso: Option<String>,
+
+    /// The timezone increment buckets are found in, defaults to the timezone provided by --output-tz or --tz (or UTC).
+    #[structopt(long)]
+    bucket_tz: Option<String>,
+
+    /// A timestamp that increment buckets are counted from, such as the start of a shift. Calendar month increments ignore it.
+    #[structopt(long)]
+    bucket_origin: Option<String>,
+
+    /// Move every increment bucket later by an offset (such as 6h or PT6H), a leading `-` moves them earlier.
+    #[structopt(long, allow_hyphen_values = true)]
+    bucket_offset: Option<String>,
+
+    /// A timestamp used to fill in missing parts of timestamps, such as the year of RFC 3164 syslog messages.
+    /// Defaults to the modification time of the file being read.
+    #[structopt(long)]
+    anchor: Option<String>,
+
+    /// Complete timestamps missing their year or date (such as `03-14 10:22:01`, `Mar 14` or `10:22:01.123`).
+    /// The missing parts come from the last timestamp with a full date, a date in the file name (such as `app-2021-03-14.log`) or the --anchor.
+    #[structopt(long)]
+    complete: bool,
+
+    /// The order of the day and month in dates such as 01/02/2021: `dmy`, `mdy` or `auto`.
+    /// With `auto` the order is found from the first date with a day over 12, dates that could be either order are read once it is found.
+    #[structopt(long)]
+    date_order: Option<String>,
+
     /// Select an aggregator.
     #[structopt(subcommand)]
     aggregator: Aggregators,
 
-    /// Provide a TZ to be used. If the timefield includes a timestamp this field will be disregarded.
+    /// Provide a TZ to be used, either an offset (+01:00) or an IANA name (Europe/London). If the timefield includes a timezone this field will be disregarded.
+    /// Local times repeated when clocks go back are read as set by --ambiguous-time, local times skipped when clocks go forward are an error.
     #[structopt(long = "tz")]
     timezone: Option<String>,
 
+    /// How local times repeated when clocks go back are read: `earliest`, `latest` or `error`.
+    #[structopt(long, default_value = "earliest", parse(try_from_str = parse_ambiguous_time))]
+    ambiguous_time: AmbiguousTime,
+
+    /// Convert timestamps into a timezone for the output (an offset or an IANA name), such as split file names, transformed timestamps and increment buckets.
+    #[structopt(long)]
+    output_tz: Option<String>,
+
     /// Provide a datetime format used to parse timestamps, if not specified dta will try to parse the format.
     #[structopt(short = "f", long = "datetime-format")]
     date_format: Option<String>,
 
+    /// Read numeric timestamps counted from an epoch, otherwise the epoch is detected from the size of the number.
+    /// Epochs: unix, unix-ms, unix-us, unix-ns, filetime, webkit, cocoa, hfs or excel.
+    #[structopt(long, parse(try_from_str = parse_epoch_kind))]
+    epoch: Option<EpochKind>,
+
     /// Convert timestamp being used by the aggregation into the provided format, if none is provided the format will not be changed.
+    /// Timestamps are written in the timezone provided by --output-tz.
     #[structopt(short, long)]
     transform: Option<String>,
 }
@@ -76,7 +209,8 @@ enum Aggregators {
     Maximum,
     /// Maximums aggregation, returns the most recent date for a given increment.
     Maximums {
-        /// Increment format string (Increment formats YYYY-MM-DD or HH:MM:SS or YYYY-MM-DD HH:MM:SS).
+        /// Increment (YYYY-MM-DD, HH:MM:SS, YYYY-MM-DD HH:MM:SS, an ISO 8601 duration such as P1W or PT15M, or a shorthand such as 15m, 1h, 1d, 1w, 1mo, 1q or 1y).
+        /// Months and years follow the calendar and weeks are ISO weeks, buckets start in the timezone provided by --tz.
         #[structopt(short, long)]
         increment: String,
     },
@@ -84,7 +218,8 @@ enum Aggregators {
     Minimum,
     /// Minimums aggregation, returns the earliest date for a given increment.
     Minimums {
-        /// Increment format string (Increment formats YYYY-MM-DD or HH:MM:SS or YYYY-MM-DD HH:MM:SS).
+        /// Increment (YYYY-MM-DD, HH:MM:SS, YYYY-MM-DD HH:MM:SS, an ISO 8601 duration such as P1W or PT15M, or a shorthand such as 15m, 1h, 1d, 1w, 1mo, 1q or 1y).
+        /// Months and years follow the calendar and weeks are ISO weeks, buckets start in the timezone provided by --tz.
         #[structopt(short, long)]
         increment: String,
     },
@@ -92,7 +227,8 @@ enum Aggregators {
     Count,
     /// Counts aggregation, returns the counts of data for a given increment.
     Counts {
-        /// Increment format string (Increment formats YYYY-MM-DD or HH:MM:SS or YYYY-MM-DD HH:MM:SS).
+        /// Increment (YYYY-MM-DD, HH:MM:SS, YYYY-MM-DD HH:MM:SS, an ISO 8601 duration such as P1W or PT15M, or a shorthand such as 15m, 1h, 1d, 1w, 1mo, 1q or 1y).
+        /// Months and years follow the calendar and weeks are ISO weeks, buckets start in the timezone provided by --tz.
         #[structopt(short, long)]
         increment: String,
     },
@@ -103,20 +239,232 @@ enum Aggregators {
         output_directory: PathBuf,
         /// Provide a filename including date time format options, this is run against the relevant timestamp.
         /// The resulting string is used as the filename that data is sent to.
+        /// `{origin}` is replaced with the file or archive member the data was read from.
         #[structopt(short = "i", long)]
         filename: String,
     },
     Range {
-        /// The start of the range being selected.
-        #[structopt(short, long)]
-        start: String,
-        /// The end of the range being selected.
+        /// The start of the range being selected, a timestamp, a partial date (2021-03 is the whole month), now, today, yesterday or a time relative to now (-2h).
+        /// Can be given several times to select several ranges, each start is paired with the end in the same position.
+        #[structopt(short, long, allow_hyphen_values = true, number_of_values = 1)]
+        start: Vec<String>,
+        /// The end of the range being selected, defaults to now (or the end of a partial date start). A relative end starting with + is relative to the start (+6h).
+        #[structopt(short, long, allow_hyphen_values = true, number_of_values = 1)]
+        end: Vec<String>,
+        /// A CSV or YAML file of ranges with start, end and name fields.
         #[structopt(short, long)]
-        end: Option<String>,
-        /// Match on everything outside of the range provided.
+        ranges: Option<PathBuf>,
+        /// Write the name of the range each record matched as a field in front of it, for CSV or Plaso input.
+        #[structopt(long)]
+        tag_windows: bool,
+        /// Only match records in a recurring window of days and times, such as "mon-fri 09:00-17:00" or "weekends", in the timezone provided by --tz.
+        /// Can be given several times.
+        #[structopt(long, number_of_values = 1)]
+        every: Vec<String>,
+        /// Match on everything outside of the ranges provided.
         #[structopt(short, long)]
         inverted: bool,
     },
+    /// Sniff the timestamp format, reports which formats of the dictionary (--formats) and the built in formats read a sample of the input.
+    Sniff {
+        /// The number of timestamps sampled.
+        #[structopt(short = "n", long, default_value = "100")]
+        lines: usize,
+    },
+}
+
+/// Parse a single character CSV option, allowing names for characters that are awkward to type.
+fn parse_csv_char(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" => Ok(b'\t'),
+        "pipe" => Ok(b'|'),
+        "semicolon" => Ok(b';'),
+        s if s.len() == 1 => Ok(s.as_bytes()[0]),
+        s => Err(format!("\"{}\" is not a single character", s)),
+    }
+}
+
+/// Read how ambiguous local times are read.
+fn parse_ambiguous_time(s: &str) -> Result<AmbiguousTime, String> {
+    AmbiguousTime::new(s).map_err(|e| e.reason)
+}
+
+/// Read an epoch name.
+fn parse_epoch_kind(s: &str) -> Result<EpochKind, String> {
+    match EpochKind::from_name(s) {
+        Some(k) => Ok(k),
+        None => Err(format!(
+            "\"{}\" is not an epoch, epochs available: {}",
+            s,
+            EpochKind::NAMES.join(", ")
+        )),
+    }
+}
+
+/// Create the parser selected by the command line options, only one parser can be selected.
+fn create_parser(opt: &Opt) -> date_time_aggregator::Result<Box<dyn Parser>> {
+    let selected = [
+        opt.csv.is_some(),
+        opt.json.is_some(),
+        opt.logfmt.is_some(),
+        opt.regex.is_some(),
+        opt.syslog,
+        opt.access.is_some(),
+        opt.w3c,
+        opt.bodyfile,
+        opt.plaso.is_some(),
+    ];
+    if selected.iter().filter(|s| **s).count() > 1 {
+        return Err(Error {
+            reason: "You can select only one parser (CSV, JSON, logfmt, regex, syslog, access log, W3C, bodyfile or Plaso)"
+                .to_string(),
+            kind: ErrorKind::Parser,
+        });
+    }
+    let template = [&opt.csv, &opt.json, &opt.logfmt]
+        .iter()
+        .filter_map(|f| f.as_deref())
+        .any(TimestampTemplate::is_template);
+    if template && opt.transform.is_some() {
+        return Err(Error {
+            reason: "A transform cannot be applied to a timestamp built from a template of several fields".to_string(),
+            kind: ErrorKind::Parser,
+        });
+    }
+    if let Some(c) = opt.csv.as_ref() {
+        let mut csv_parser = CsvParser::new(c)?.delimiter(opt.delimiter).quote(opt.quote);
+        if opt.headers || opt.no_headers {
+            csv_parser = csv_parser.has_headers(opt.headers);
+        }
+        return Ok(Box::new(csv_parser));
+    }
+    if let Some(j) = opt.json.as_ref() {
+        return Ok(Box::new(JsonParser::new(j)?));
+    }
+    if let Some(l) = opt.logfmt.as_ref() {
+        return Ok(Box::new(LogfmtParser::new(l)?));
+    }
+    if let Some(r) = opt.regex.as_ref() {
+        return Ok(Box::new(RegexParser::from_pattern_or_preset(r)?));
+    }
+    if opt.syslog {
+        return Ok(Box::new(SyslogParser::new(parse_anchor(opt)?)));
+    }
+    if let Some(a) = opt.access.as_ref() {
+        return Ok(Box::new(AccessLogParser::new(a)?));
+    }
+    if opt.w3c {
+        if opt.transform.is_some() {
+            return Err(Error {
+                reason: "A transform cannot be applied to W3C data, the timestamp is split over the date and time fields".to_string(),
+                kind: ErrorKind::Parser,
+            });
+        }
+        return Ok(Box::new(W3cParser::default()));
+    }
+    if opt.bodyfile {
+        return Ok(Box::new(BodyfileParser));
+    }
+    if let Some(p) = opt.plaso.as_ref() {
+        return Ok(Box::new(PlasoParser::new(PlasoLayout::new(p)?)?));
+    }
+    Ok(Box::new(SimpleParser))
+}
+
+fn parse_anchor(opt: &Opt) -> date_time_aggregator::Result<Option<DateTime<FixedOffset>>> {
+    match opt.anchor.as_ref() {
+        Some(a) => Ok(Some(
+            Data::new(a, None, None, opt.timezone.as_ref(), Vec::new())?.timestamp,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Update the aggregator with parsed entries in the output timezone, reporting entries that could not be parsed.
+fn aggregate(
+    aggregator: &mut Box<dyn Aggregator>,
+    parsed: date_time_aggregator::Result<Vec<Data>>,
+    origin: &Option<String>,
+    output_tz: Option<Timezone>,
+) {
+    match parsed {
+        Ok(entries) => {
+            for mut d in entries {
+                d.origin = origin.clone();
+                if let Some(tz) = output_tz {
+                    d.timestamp = tz.from_utc(&d.timestamp.with_timezone(&Utc));
+                }
+                if let Err(e) = aggregator.update(&d) {
+                    eprintln!("Error occured in parsing: {:?}", e)
+                }
+            }
+        }
+        Err(Error {
+            kind: ErrorKind::Skipped,
+            reason,
+        }) => debug!("{}", reason),
+        Err(e) => eprintln!("Error occured in parsing: {:?}", e),
+    }
+}
+
+/// Whether the entry has a date that could be day or month first.
+fn ambiguous(parsed: &date_time_aggregator::Result<Vec<Data>>) -> bool {
+    matches!(
+        parsed,
+        Err(Error {
+            kind: ErrorKind::Ambiguous,
+            ..
+        })
+    )
+}
+
+/// Create the alignment of increment buckets, they start at calendar boundaries in the bucket timezone (or the timezone provided).
+fn create_alignment(opt: &Opt) -> date_time_aggregator::Result<Alignment> {
+    let timezone = match opt
+        .bucket_tz
+        .as_ref()
+        .or(opt.output_tz.as_ref())
+        .or(opt.timezone.as_ref())
+    {
+        Some(t) => Timezone::new(t)?,
+        None => Timezone::default(),
+    };
+    let origin = match opt.bucket_origin.as_ref() {
+        Some(o) => Some(Data::new(o, None, None, opt.timezone.as_ref(), Vec::new())?.timestamp),
+        None => None,
+    };
+    let offset = match opt.bucket_offset.as_ref() {
+        Some(o) => Increment::parse_offset(o)?,
+        None => chrono::Duration::zero(),
+    };
+    Ok(Alignment {
+        timezone,
+        origin,
+        offset,
+    })
+}
+
+/// Create the windows of a range aggregation from the start and end options and a ranges file.
+fn create_windows(
+    start: Vec<String>,
+    end: Vec<String>,
+    ranges: Option<PathBuf>,
+    tz: Option<&String>,
+) -> date_time_aggregator::Result<Vec<Window>> {
+    if end.len() > start.len() {
+        return Err(Error {
+            reason: "Every --end needs a --start".to_string(),
+            kind: ErrorKind::Aggregator,
+        });
+    }
+    let mut windows = match ranges {
+        Some(r) => Window::from_file(&r, tz)?,
+        None => Vec::new(),
+    };
+    for (i, s) in start.iter().enumerate() {
+        windows.push(Window::new(None, s, end.get(i).map(String::as_str), tz)?);
+    }
+    Ok(windows)
 }
 
 fn main() {
@@ -133,44 +481,148 @@ fn main() {
         debug!("Command line options provided: {:#?}", opt);
     }
 
-    let mut formats = match opt.formats {
-        None => None,
-        Some(f) => match date_time_aggregator::FormatDictionary::from_file(f) {
-            Ok(f) => Some(f),
+    if let Some(Err(e)) = opt.timezone.as_ref().map(|t| Timezone::new(t)) {
+        eprintln!("Error whilst reading timezone: {}", e.reason);
+        std::process::exit(1);
+    }
+    let output_tz = match opt.output_tz.as_deref().map(Timezone::new).transpose() {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error whilst reading output timezone: {}", e.reason);
+            std::process::exit(1);
+        }
+    };
+    let transform = opt.transform.as_deref().map(|t| match output_tz {
+        Some(tz) => Transform::new(t).timezone(tz),
+        None => Transform::new(t),
+    });
+
+    let date_order = match opt.date_order.as_deref().map(DateOrder::new).transpose() {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("Error whilst reading date order: {}", e.reason);
+            std::process::exit(1);
+        }
+    };
+    // The bundled formats are only tried with -F or when sniffing, completing or ordering dates, a date format provided with them is tried first.
+    // Otherwise an empty dictionary carries a choice of ambiguous time other than the earliest
+    let sniffing = matches!(opt.aggregator, Aggregators::Sniff { .. });
+    let formats = match opt.formats.clone() {
+        Some(f) => FormatDictionary::bundled()
+            .and_then(|b| Ok(Some(b.merge(FormatDictionary::from_file(f)?)))),
+        None if sniffing || opt.complete || date_order.is_some() => {
+            FormatDictionary::bundled().map(Some)
+        }
+        None if opt.ambiguous_time != AmbiguousTime::Earliest => {
+            Ok(Some(FormatDictionary::default()))
+        }
+        None => Ok(None),
+    }
+    .map(|d| match (d, opt.date_format.as_ref()) {
+        (Some(d), Some(f)) => Some(d.prefer("date-format", f)),
+        (d, _) => d,
+    })
+    .map(|d| d.map(|d| d.read_ambiguous(opt.ambiguous_time)));
+    let mut formats = match formats {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error whilst creating Format Dictionary: {}", e.reason);
+            std::process::exit(1);
+        }
+    };
+    if let Some(p) = opt.priority.as_ref().filter(|p| p.exists()) {
+        formats = match formats.map(|f| f.load_priority(p)).transpose() {
+            Ok(f) => f,
             Err(e) => {
-                eprintln!("Error whilst creating Format Dictionary: {}", e.reason);
+                eprintln!("Error whilst reading format priority: {}", e.reason);
                 std::process::exit(1);
             }
-        },
-    };
+        };
+    }
+    if let Some(mut dictionary) = formats.take() {
+        if opt.complete {
+            let anchor = match parse_anchor(&opt) {
+                Ok(a) => a,
+                Err(e) => {
+                    eprintln!("Error whilst reading anchor: {}", e.reason);
+                    std::process::exit(1);
+                }
+            };
+            dictionary = dictionary.complete(anchor);
+        }
+        if let Some(order) = date_order {
+            dictionary = dictionary.resolve_date_order(order);
+        }
+        formats = Some(dictionary);
+    }
 
     // Match based on the command line options to decide what todo.
-    let mut source: Box<dyn Source> = match opt.glob {
-        Some(ref g) => match FileSource::new(&g, true) {
-            Ok(s) => (Box::new(s) as Box<dyn Source>),
+    let mut source: Box<dyn Source> = match (opt.archive.as_ref(), opt.glob.as_ref()) {
+        (Some(a), _) => match ArchiveSource::new(a.clone(), opt.members.as_deref()) {
+            Ok(s) => Box::new(s) as Box<dyn Source>,
             Err(e) => {
                 eprintln!("Error whilst creating source: {}", e.reason);
                 std::process::exit(1);
             }
         },
-        None => (Box::new(StdinSource::default()) as Box<dyn Source>),
+        (None, Some(g)) => match FileSource::new(g, true) {
+            Ok(s) if opt.follow => {
+                Box::new(s.follow(Duration::from_millis(FOLLOW_POLL_MS))) as Box<dyn Source>
+            }
+            Ok(s) => Box::new(s) as Box<dyn Source>,
+            Err(e) => {
+                eprintln!("Error whilst creating source: {}", e.reason);
+                std::process::exit(1);
+            }
+        },
+        (None, None) => Box::new(StdinSource::default()) as Box<dyn Source>,
     };
+    if opt.follow && (opt.glob.is_none() || opt.archive.is_some()) {
+        eprintln!("Error whilst creating source: --follow can only be used with an input glob");
+        std::process::exit(1);
+    }
 
-    let parser: Box<dyn Parser> = match (opt.csv.as_ref(), opt.json.as_ref()) {
-        (Some(_c), Some(_j)) => {
-            // Error
-            eprintln!("Error whilst creating parser: You can select either CSV or JSON");
+    let mut parser = match create_parser(&opt) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error whilst creating parser: {}", e.reason);
             std::process::exit(1);
         }
-        (Some(c), None) => (Box::new(CsvParser::new(*c)) as Box<dyn Parser>),
-        (None, Some(j)) => (Box::new(JsonParser::new(j)) as Box<dyn Parser>),
-        (None, None) => (Box::new(SimpleParser::default()) as Box<dyn Parser>),
     };
 
+    if let Aggregators::Sniff { lines } = opt.aggregator {
+        // Parsers pass the timestamps they find to a sampling dictionary
+        let mut dictionary = formats.unwrap_or_default().sample();
+        while dictionary.samples().len() < lines {
+            match source.read_data() {
+                Ok(r) if !r.is_empty() => {
+                    let _ = parser.parse_entries(
+                        r,
+                        None,
+                        None,
+                        opt.timezone.as_ref(),
+                        Some(&mut dictionary),
+                        None,
+                    );
+                }
+                _ => break,
+            }
+        }
+        println!("{}", Report::new(dictionary.samples(), &dictionary));
+        return;
+    }
+
+    let alignment = match create_alignment(&opt) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("Error whilst creating aggregator: {}", e.reason);
+            std::process::exit(1);
+        }
+    };
     let mut aggregator: Box<dyn Aggregator> = match opt.aggregator.clone() {
         Aggregators::Maximum => Box::new(MaximumAggregator::default()),
         Aggregators::Maximums { increment } => match MaximumsAggregator::new(increment) {
-            Ok(a) => Box::new(a),
+            Ok(a) => Box::new(a.align(alignment)),
             Err(e) => {
                 eprintln!("Error whilst creating aggregator: {}", e);
                 std::process::exit(1);
@@ -178,7 +630,7 @@ fn main() {
         },
         Aggregators::Minimum => Box::new(MinimumAggregator::default()),
         Aggregators::Minimums { increment } => match MinimumsAggregator::new(increment) {
-            Ok(a) => Box::new(a),
+            Ok(a) => Box::new(a.align(alignment)),
             Err(e) => {
                 eprintln!("Error whilst creating aggregator: {}", e);
                 std::process::exit(1);
@@ -186,7 +638,7 @@ fn main() {
         },
         Aggregators::Count => Box::new(CountAggregator::default()),
         Aggregators::Counts { increment } => match CountsAggregator::new(increment) {
-            Ok(a) => Box::new(a),
+            Ok(a) => Box::new(a.align(alignment)),
             Err(e) => {
                 eprintln!("Error whilst creating aggregator: {}", e);
                 std::process::exit(1);
@@ -205,32 +657,159 @@ fn main() {
         Aggregators::Range {
             start,
             end,
+            ranges,
+            tag_windows,
+            every,
             inverted,
-        } => match RangeAggregator::new(start, end, inverted) {
+        } => match create_windows(start, end, ranges, opt.timezone.as_ref()).and_then(|w| {
+            let timezone = match opt.timezone.as_ref() {
+                Some(t) => Timezone::new(t)?,
+                None => Timezone::default(),
+            };
+            let recurring = every
+                .iter()
+                .map(|e| Recurring::new(e, timezone))
+                .collect::<date_time_aggregator::Result<Vec<Recurring>>>()?;
+            if w.is_empty() && recurring.is_empty() {
+                return Err(Error {
+                    reason: "Provide a range with --start, --ranges or --every".to_string(),
+                    kind: ErrorKind::Aggregator,
+                });
+            }
+            // The window is written as a CSV field, so only CSV records can be tagged
+            let (delimiter, quote) = match (opt.csv.is_some(), opt.plaso.is_some()) {
+                (true, _) => (opt.delimiter, opt.quote),
+                (false, false) if tag_windows => {
+                    return Err(Error {
+                        reason: "Windows can only be tagged on CSV or Plaso input".to_string(),
+                        kind: ErrorKind::Aggregator,
+                    })
+                }
+                _ => (b',', b'"'),
+            };
+            Ok(RangeAggregator::from_windows(w, inverted)
+                .recurring(recurring)
+                .tag_windows(tag_windows)
+                .csv(delimiter, quote))
+        }) {
             Ok(a) => Box::new(a) as Box<dyn Aggregator>,
             Err(e) => {
                 eprintln!("Error whilst creating aggregator: {}", e);
                 std::process::exit(1);
             }
         },
+        Aggregators::Sniff { .. } => unreachable!("Sniffing does not aggregate"),
     };
+    let interval = Duration::from_secs(opt.interval.unwrap_or(SNAPSHOT_INTERVAL_SECS));
+    let mut last_snapshot = Instant::now();
+    let mut header = None;
+    let mut origin = None;
+    // Entries from the first date that could be day or month first, kept until the order is found
+    let mut pending = Vec::new();
+    let keep_ambiguous = date_order == Some(None);
     while let Ok(r) = source.read_data() {
+        if opt.follow && last_snapshot.elapsed() >= interval {
+            match aggregator.snapshot() {
+                Ok(s) if !s.is_empty() => println!("{}", s),
+                Ok(_) => (),
+                Err(e) => eprintln!("{}", e),
+            }
+            last_snapshot = Instant::now();
+        }
         if r.is_empty() {
+            // When following, an empty read means no new data has arrived yet
+            if opt.follow {
+                continue;
+            }
             break;
         }
-        match parser.parse_data(
+        if source.origin() != origin {
+            origin = source.origin();
+            parser.set_origin(origin.as_deref());
+            let modified = source.modified();
+            if let (Some(o), None, true, None) = (&origin, modified, opt.syslog, &opt.anchor) {
+                eprintln!(
+                    "No modification time for {}, the year of syslog timestamps is inferred from the current time",
+                    o
+                );
+            }
+            parser.set_modified(modified);
+            if let Some(f) = formats.as_mut() {
+                f.set_origin(origin.as_deref());
+            }
+        }
+        let kept = match keep_ambiguous {
+            true => Some(r.clone()),
+            false => None,
+        };
+        let parsed = parser.parse_entries(
             r,
             opt.date_format.as_ref(),
+            opt.epoch,
             opt.timezone.as_ref(),
             formats.as_mut(),
-            opt.transform.as_ref(),
-        ) {
-            Ok(d) => {
-                if let Err(e) = aggregator.update(&d) {
-                    eprintln!("Error occured in parsing: {:?}", e)
+            transform.as_ref(),
+        );
+        // The header can change between files or part way through one (W3C #Fields directives)
+        if let Some(h) = parser.header().filter(|h| header.as_ref() != Some(h)) {
+            aggregator.set_header(h.clone());
+            header = Some(h);
+        }
+        let resolved = formats.as_ref().and_then(|f| f.date_order()).is_some();
+        let parsed = match kept {
+            // Entries after an ambiguous date are kept as well so they are aggregated in input order
+            Some(raw) if !resolved && (ambiguous(&parsed) || !pending.is_empty()) => {
+                pending.push((parsed, raw, origin.clone()));
+                if pending.len() < PENDING_LIMIT {
+                    continue;
+                }
+                eprintln!(
+                    "Warning: {} entries were read without a date showing whether the day or month is first, reading dates day first. Provide the order with --date-order dmy or mdy",
+                    pending.len()
+                );
+                if let Some(f) = formats.as_mut() {
+                    f.assume_date_order(DateOrder::DayFirst);
                 }
+                None
             }
-            Err(e) => eprintln!("Error occured in parsing: {:?}", e),
+            _ => Some(parsed),
+        };
+        for (p, raw, o) in std::mem::take(&mut pending) {
+            let p = match ambiguous(&p) {
+                true => parser.parse_entries(
+                    raw,
+                    opt.date_format.as_ref(),
+                    opt.epoch,
+                    opt.timezone.as_ref(),
+                    formats.as_mut(),
+                    transform.as_ref(),
+                ),
+                false => p,
+            };
+            aggregate(&mut aggregator, p, &o, output_tz);
+        }
+        if let Some(p) = parsed {
+            aggregate(&mut aggregator, p, &origin, output_tz);
+        }
+    }
+    let mut unresolved = Vec::new();
+    for (p, raw, o) in pending {
+        match ambiguous(&p) {
+            true => unresolved.push(raw),
+            false => aggregate(&mut aggregator, p, &o, output_tz),
+        }
+    }
+    if let Some(raw) = unresolved.first() {
+        eprintln!(
+            "Error: {} entries such as \"{}\" have dates that could be day or month first, provide the order with --date-order dmy or mdy",
+            unresolved.len(),
+            String::from_utf8_lossy(raw)
+        );
+    }
+    if let Some(f) = formats.as_ref() {
+        debug!("Format dictionary entries matched: {:?}", f.hits());
+        if let Some(Err(e)) = opt.priority.as_ref().map(|p| f.save_priority(p)) {
+            eprintln!("Error whilst saving format priority: {}", e.reason);
         }
     }
     match aggregator.return_value() {
@@ -0,0 +1,255 @@
+//! # Format Sniffing
+//!
+//! Sniffing reports which formats read a sample of timestamps, so the format of a new data set can be found before aggregating it.
+//!
+//! Every format of a [`FormatDictionary`] is tried along with the formats tried when no format is provided, numeric timestamps are matched to the epoch detected from their magnitude.
+//! Timestamps that matching formats read as different times (such as `03/04/2021` read day first and month first) are reported as ambiguous.
+//!
+
+use crate::{
+    data::parsing::{self, EpochKind},
+    FormatDictionary,
+};
+use chrono::{DateTime, NaiveDateTime};
+use std::{cmp::Reverse, fmt};
+
+/// The formats tried by [`parse_dt`](parsing::parse_dt) and [`parse_naive_dt`](parsing::parse_naive_dt) when no format is provided, besides RFC 2822 and RFC 3339.
+const BUILT_IN_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S %z",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%a, %d %b %Y %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S%.f",
+];
+
+/// Examples of ambiguous and unmatched timestamps shown in a report.
+const EXAMPLES: usize = 3;
+
+/// A way of reading timestamps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Candidate {
+    /// An entry of the dictionary, its name and format.
+    Dictionary(String, String),
+    /// A format tried when no format is provided.
+    BuiltIn(&'static str),
+    Rfc2822,
+    Rfc3339,
+    /// Numeric timestamps counted from an epoch.
+    Epoch(EpochKind),
+}
+
+impl Candidate {
+    /// Read a timestamp, times with an offset are read as UTC so they can be compared.
+    fn parse(&self, s: &str) -> Option<NaiveDateTime> {
+        let format = |fmt: &str| {
+            DateTime::parse_from_str(s, fmt)
+                .map(|d| d.naive_utc())
+                .or_else(|_| NaiveDateTime::parse_from_str(s, fmt))
+                .ok()
+        };
+        match self {
+            Candidate::Dictionary(_, fmt) => format(fmt),
+            Candidate::BuiltIn(fmt) => format(fmt),
+            Candidate::Rfc2822 => DateTime::parse_from_rfc2822(s).ok().map(|d| d.naive_utc()),
+            Candidate::Rfc3339 => DateTime::parse_from_rfc3339(s).ok().map(|d| d.naive_utc()),
+            Candidate::Epoch(kind) => match s.parse::<f64>() {
+                Ok(f) if EpochKind::detect(f) == *kind => {
+                    parsing::parse_epoch(s, Some(*kind), None).map(|d| d.naive_utc())
+                }
+                _ => None,
+            },
+        }
+    }
+    /// The option passed to dta to read timestamps this way, if one is needed.
+    pub fn option(&self) -> Option<String> {
+        match self {
+            Candidate::Dictionary(_, fmt) => Some(format!("-f {:?}", fmt)),
+            Candidate::BuiltIn(fmt) => Some(format!("-f {:?}", fmt)),
+            Candidate::Epoch(kind) => Some(format!("--epoch {}", kind.name())),
+            Candidate::Rfc2822 | Candidate::Rfc3339 => None,
+        }
+    }
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Candidate::Dictionary(name, fmt) => write!(f, "{:?} (dictionary entry {})", fmt, name),
+            Candidate::BuiltIn(fmt) => write!(f, "{:?} (built in)", fmt),
+            Candidate::Rfc2822 => write!(f, "RFC 2822 (built in)"),
+            Candidate::Rfc3339 => write!(f, "RFC 3339 (built in)"),
+            Candidate::Epoch(kind) => write!(f, "{} epoch", kind.name()),
+        }
+    }
+}
+
+/// The formats matching a sample of timestamps.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The number of timestamps sampled.
+    pub samples: usize,
+    /// Each candidate matching any timestamp and the number of timestamps it matched, the most matches first.
+    pub matches: Vec<(Candidate, usize)>,
+    /// Timestamps that the matching candidates read as different times.
+    pub ambiguous: Vec<String>,
+    /// Timestamps that no candidate matched.
+    pub unmatched: Vec<String>,
+}
+
+impl Report {
+    /// Try every format of the dictionary and the built in formats against the samples.
+    pub fn new(samples: &[String], dictionary: &FormatDictionary) -> Self {
+        let mut candidates: Vec<Candidate> = dictionary
+            .formats()
+            .into_iter()
+            .map(|(name, fmt)| Candidate::Dictionary(name.to_string(), fmt.to_string()))
+            .chain(BUILT_IN_FORMATS.iter().map(|f| Candidate::BuiltIn(f)))
+            .chain(vec![Candidate::Rfc2822, Candidate::Rfc3339])
+            .collect();
+        for kind in samples
+            .iter()
+            .filter_map(|s| s.parse::<f64>().ok())
+            .map(EpochKind::detect)
+        {
+            if !candidates.contains(&Candidate::Epoch(kind)) {
+                candidates.push(Candidate::Epoch(kind));
+            }
+        }
+        let mut counts = vec![0; candidates.len()];
+        let mut ambiguous = Vec::new();
+        let mut unmatched = Vec::new();
+        for sample in samples {
+            let mut read: Vec<NaiveDateTime> = Vec::new();
+            for (i, candidate) in candidates.iter().enumerate() {
+                if let Some(dt) = candidate.parse(sample) {
+                    counts[i] += 1;
+                    if !read.contains(&dt) {
+                        read.push(dt);
+                    }
+                }
+            }
+            match read.len() {
+                0 => unmatched.push(sample.clone()),
+                1 => (),
+                _ => ambiguous.push(sample.clone()),
+            }
+        }
+        let mut matches: Vec<(Candidate, usize)> = candidates
+            .into_iter()
+            .zip(counts)
+            .filter(|(_, c)| *c > 0)
+            .collect();
+        // Sorting is stable so dictionary entries come before the built in formats
+        matches.sort_by_key(|(_, c)| Reverse(*c));
+        Self {
+            samples: samples.len(),
+            matches,
+            ambiguous,
+            unmatched,
+        }
+    }
+    /// The candidate matching the most timestamps.
+    pub fn recommended(&self) -> Option<&Candidate> {
+        self.matches.first().map(|(c, _)| c)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Sampled {} timestamps", self.samples)?;
+        for (candidate, count) in self.matches.iter() {
+            writeln!(
+                f,
+                "{:>6.1}% {:>6} {}",
+                *count as f64 * 100.0 / self.samples as f64,
+                count,
+                candidate
+            )?;
+        }
+        let examples = |samples: &[String]| {
+            samples
+                .iter()
+                .take(EXAMPLES)
+                .map(|s| format!("{:?}", s))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        if !self.ambiguous.is_empty() {
+            writeln!(
+                f,
+                "{} timestamps are read as different times by the formats matching them, such as {}",
+                self.ambiguous.len(),
+                examples(&self.ambiguous)
+            )?;
+        }
+        if !self.unmatched.is_empty() {
+            writeln!(
+                f,
+                "{} timestamps matched no format, such as {}",
+                self.unmatched.len(),
+                examples(&self.unmatched)
+            )?;
+        }
+        match self.recommended() {
+            None => write!(f, "No format to recommend"),
+            Some(candidate) => {
+                write!(f, "Recommended: {}", candidate)?;
+                match candidate {
+                    Candidate::BuiltIn(_) | Candidate::Rfc2822 | Candidate::Rfc3339 => {
+                        write!(
+                            f,
+                            "\nNo format needs to be provided, it is tried by default"
+                        )
+                    }
+                    Candidate::Dictionary(..) => {
+                        write!(
+                            f,
+                            "\nUse the dictionary with -F or provide the format with "
+                        )?;
+                        write!(f, "{}", candidate.option().unwrap_or_default())
+                    }
+                    Candidate::Epoch(_) => {
+                        write!(f, "\nUse {}", candidate.option().unwrap_or_default())
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //
+    use super::*;
+    #[test]
+    fn report_matches_and_ambiguity() {
+        let mut dictionary = FormatDictionary::default();
+        dictionary.insert("dmy", "%d/%m/%Y %H:%M:%S");
+        dictionary.insert("mdy", "%m/%d/%Y %H:%M:%S");
+        let samples: Vec<String> = vec![
+            "03/04/2021 10:00:00",
+            "13/04/2021 10:00:00",
+            "2021-03-04T10:00:00+00:00",
+            "not a timestamp",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        let report = Report::new(&samples, &dictionary);
+        assert_eq!(
+            report.matches,
+            vec![
+                (
+                    Candidate::Dictionary("dmy".to_string(), "%d/%m/%Y %H:%M:%S".to_string()),
+                    2
+                ),
+                (
+                    Candidate::Dictionary("mdy".to_string(), "%m/%d/%Y %H:%M:%S".to_string()),
+                    1
+                ),
+                (Candidate::Rfc3339, 1),
+            ]
+        );
+        assert_eq!(report.ambiguous, vec!["03/04/2021 10:00:00".to_string()]);
+        assert_eq!(report.unmatched, vec!["not a timestamp".to_string()]);
+    }
+}